@@ -1,88 +1,431 @@
-use crate::law_section::{collect_law_sections, LawSections};
+use crate::{
+    config::Config,
+    error::SpringbokError,
+    http::{fetch, fetch_bytes},
+    law_section::{collect_law_sections, LawSections, LawSource},
+    ma_legislature::{get_general_court, SearchEntry},
+};
 use fancy_regex::Regex;
-use log::info;
-use scraper::{Html, Selector};
+use log::{debug, info, trace, warn};
+use scraper::{Element, Html, Selector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-pub fn get_bill_text_nodes(bill_url: &Url) -> Vec<String> {
-    // Get the bill summary page
-    let bill_body = reqwest::blocking::get(bill_url.clone())
-        .unwrap()
-        .text()
-        .unwrap();
-    let bill_document = Html::parse_document(bill_body.as_str());
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Bill {
+    pub bill_number: String,
+    pub title: String,
+    pub sponsor: String,
+    pub general_court: String,
+    // Text before the first section, such as an emergency preamble
+    pub preamble: Option<String>,
+    pub bill_sections: Vec<BillSection>,
+}
+
+pub fn collect_bill(
+    config: &Config,
+    bill_number: &str,
+    search_entry: &SearchEntry,
+) -> Result<Bill, SpringbokError> {
+    // Get the bill summary page, and parse the bill metadata
+    let bill_url = &search_entry.bill_url;
+    let bill_body = fetch(config, bill_url)?;
+    let mut bill = parse_bill_metadata(&bill_body, bill_number, search_entry);
+
+    // Get the bill text, and collect the bill sections
+    let text_nodes = get_bill_text_nodes(config, bill_url, &bill_body)?;
+    let section_regex = init_bill_section_regex();
+    bill.preamble = collect_bill_preamble(&text_nodes, &section_regex);
+    bill.bill_sections = collect_bill_sections(&text_nodes, &section_regex);
+    Ok(bill)
+}
+
+pub fn parse_bill_metadata(html: &str, bill_number: &str, search_entry: &SearchEntry) -> Bill {
+    // Use the search entry for any metadata missing from the bill summary page
+    let bill_document = Html::parse_document(html);
+    let title_selector = Selector::parse("h2").unwrap();
+    let title = bill_document
+        .select(&title_selector)
+        .next()
+        .map(|title_element| title_element.text().collect::<String>().trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| search_entry.bill_summary.clone());
+
+    // The sponsor follows a "Sponsor:" term
+    let term_selector = Selector::parse("dt").unwrap();
+    let sponsor = bill_document
+        .select(&term_selector)
+        .find(|term_element| term_element.text().collect::<String>().contains("Sponsor"))
+        .and_then(|term_element| term_element.next_sibling_element())
+        .map(|sponsor_element| {
+            sponsor_element
+                .text()
+                .collect::<String>()
+                .trim()
+                .to_string()
+        })
+        .filter(|sponsor| !sponsor.is_empty())
+        .unwrap_or_else(|| search_entry.bill_sponsor.clone());
 
+    let general_court = get_general_court(&search_entry.bill_url);
+
+    Bill {
+        bill_number: bill_number.to_string(),
+        title,
+        sponsor,
+        general_court,
+        preamble: None,
+        bill_sections: Vec::new(),
+    }
+}
+
+pub fn get_bill_text_nodes(
+    config: &Config,
+    bill_url: &Url,
+    bill_body: &str,
+) -> Result<Vec<String>, SpringbokError> {
     // Select the bill text URL
-    let text_url_selector = Selector::parse("div.modalBtnGroup a:nth-child(1)").unwrap();
-    let text_url_element = bill_document.select(&text_url_selector).next().unwrap();
-    let text_url = Url::parse("https://malegislature.gov")
-        .unwrap()
-        .join(text_url_element.value().attr("href").unwrap().trim())
-        .unwrap();
+    let text_href = get_bill_text_href(bill_body).map_err(|error| error.with_url(bill_url))?;
+    let text_url = config.base_url.join(text_href.trim()).map_err(|error| {
+        SpringbokError::parse(format!("Invalid bill text URL {text_href:?}: {error}"))
+    })?;
     info!("Value for text URL: {}", text_url);
 
+    // Some bills give their text only as a PDF
+    if is_pdf_url(&text_url) {
+        let pdf = fetch_bytes(config, &text_url)?;
+        let pdf_text = extract_pdf_text(&pdf).map_err(|error| error.with_url(&text_url))?;
+        return Ok(get_bill_text_nodes_from_pdf_text(&pdf_text));
+    }
+
     // Get the bill text page
-    let text_body = reqwest::blocking::get(text_url).unwrap().text().unwrap();
-    let text_document = Html::parse_document(text_body.as_str());
+    let text_body = fetch(config, &text_url)?;
+    get_bill_text_nodes_from_html(&text_body).map_err(|error| error.with_url(&text_url))
+}
+
+// Bill pages usually link to the text from a modal button group, but some, such as amendments, don't,
+// and some only link to a PDF
+pub(crate) const BILL_TEXT_SELECTORS: [&str; 4] = [
+    "div.modalBtnGroup a:nth-child(1)",
+    r#"a[href*="BillText"]"#,
+    r#"a[href$="/Text"]"#,
+    r#"a[href$=".pdf" i]"#,
+];
+
+pub fn get_bill_text_href(html: &str) -> Result<String, SpringbokError> {
+    // Try each selector in turn, using the first link found
+    let bill_document = Html::parse_document(html);
+    BILL_TEXT_SELECTORS
+        .iter()
+        .find_map(|selector| {
+            let text_url_selector = Selector::parse(selector).unwrap();
+            bill_document
+                .select(&text_url_selector)
+                .next()
+                .and_then(|text_url_element| text_url_element.value().attr("href"))
+                .map(String::from)
+        })
+        .ok_or_else(|| SpringbokError::Selector {
+            element: String::from("bill text link"),
+            selectors: BILL_TEXT_SELECTORS.map(String::from).to_vec(),
+            url: None,
+        })
+}
 
-    // Select, and (optionally) print each text node of the bill text
-    let container_selector = Selector::parse("div.modal-body div").unwrap();
-    let container_element = text_document.select(&container_selector).next().unwrap();
+pub(crate) const BILL_TEXT_CONTAINER_SELECTOR: &str = "div.modal-body div";
+
+pub fn get_bill_text_nodes_from_html(html: &str) -> Result<Vec<String>, SpringbokError> {
+    let text_document = Html::parse_document(html);
+
+    // Select, and log at trace level, each text node of the bill text
+    let container_selector = Selector::parse(BILL_TEXT_CONTAINER_SELECTOR).unwrap();
+    let container_element = text_document
+        .select(&container_selector)
+        .next()
+        .ok_or_else(|| SpringbokError::Selector {
+            element: String::from("bill text"),
+            selectors: vec![String::from(BILL_TEXT_CONTAINER_SELECTOR)],
+            url: None,
+        })?;
     let mut text_nodes: Vec<String> = Vec::new();
     for text_node in container_element.text().collect::<Vec<_>>() {
-        // TODO: Restore and make optional
-        // println!("{text_node}");
+        // Whitespace between elements is only formatting
+        if text_node.trim().is_empty() {
+            continue;
+        }
+        trace!("{text_node}");
         text_nodes.push(text_node.to_string());
     }
-    text_nodes
+    Ok(text_nodes)
 }
 
 #[derive(Debug, Clone)]
 pub struct BillSectionRegex {
+    bill_section_start: Regex,
     bill_section: Regex,
+    bill_section_heading: Regex,
     amended: Regex,
     striking: Regex,
     inserting: Regex,
     repealed: Regex,
+    effective_date: Regex,
+    appropriation: Regex,
+    line_number: Regex,
 }
 
 // TODO: Document these?
 pub fn init_bill_section_regex() -> BillSectionRegex {
     BillSectionRegex {
-        bill_section: Regex::new(r"^\s*SECTION\s*(\d*\w*)\s*\.").unwrap(),
+        bill_section_start: Regex::new(r"^\s*SECTION\b").unwrap(),
+        bill_section: Regex::new(r"^\s*SECTION\s+(\d+[A-Z]?|[IVXLC]+)\s*\.").unwrap(),
+        bill_section_heading: Regex::new(r"^\s*SECTION\s+(\d+[A-Z]?|[IVXLC]+)\s*$").unwrap(),
         amended: Regex::new(r"amended").unwrap(),
         striking: Regex::new(r"striking").unwrap(),
-        inserting: Regex::new(r"inserting").unwrap(),
+        inserting: Regex::new(r"inserting|\badding the following\b").unwrap(),
         repealed: Regex::new(r"repealed").unwrap(),
+        effective_date: Regex::new(r"(?i)shall take effect").unwrap(),
+        appropriation: Regex::new(r"(?i)hereby appropriated|^\s*\$?\d{1,3}(,\d{3})+\s*$").unwrap(),
+        // A line number, alone or before the text of its line
+        line_number: Regex::new(r"^\s*(\d{1,4})(?:\s+(.*?))?\s*$").unwrap(),
     }
 }
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BillSection {
     pub section_number: String,
     pub text: String,
     pub law_sections: LawSections,
+    pub unresolved: bool,
+    // The bill's own line numbering of the section, if the bill text includes it
+    pub line_refs: Option<LineRefs>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineRefs {
+    // Bills number lines continuously, so a section's lines may start at any number
+    pub first_line: usize,
+    pub lines: Vec<String>,
+}
+
+impl LineRefs {
+    pub fn get(&self, line_number: usize) -> Option<&str> {
+        line_number
+            .checked_sub(self.first_line)
+            .and_then(|index| self.lines.get(index))
+            .map(String::as_str)
+    }
+
+    /// Lines in order of line number, with those before the first line left blank
+    pub fn line_index(&self) -> Vec<String> {
+        let mut line_index = vec![String::new(); self.first_line.saturating_sub(1)];
+        line_index.extend(self.lines.iter().cloned());
+        line_index
+    }
+}
+
+pub fn extract_line_refs(
+    section_text: &str,
+    section_regex: &BillSectionRegex,
+) -> (String, Option<LineRefs>) {
+    // Find lines which start with a number, or are only a number, and count them as line
+    // numbers only if consecutive, so a number in the text is not mistaken for one
+    let line_numbers: Vec<Option<(usize, Option<String>)>> = section_text
+        .lines()
+        .map(|line| {
+            let caps = section_regex.line_number.captures(line).unwrap()?;
+            let line_number = caps[1].parse::<usize>().ok()?;
+            Some((
+                line_number,
+                caps.get(2).map(|text| text.as_str().to_string()),
+            ))
+        })
+        .collect();
+    let numbers: Vec<usize> = line_numbers.iter().flatten().map(|(n, _)| *n).collect();
+    // A section may have one line, if it starts with its line number
+    let starts_numbered = line_numbers
+        .first()
+        .is_some_and(|line_number| line_number.as_ref().is_some_and(|(_, text)| text.is_none()));
+    if numbers.is_empty()
+        || (numbers.len() < 2 && !starts_numbered)
+        || numbers.windows(2).any(|pair| pair[1] != pair[0] + 1)
+    {
+        return (section_text.to_string(), None);
+    }
+
+    // Remove the line numbers from the text, and join the text following each number into
+    // its line. Numbered lines are printed lines, so rejoin them into paragraphs too.
+    let mut text_lines: Vec<String> = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    for (line, line_number) in section_text.lines().zip(&line_numbers) {
+        let text = match line_number {
+            Some((_, text)) => {
+                lines.push(String::new());
+                text.as_deref().unwrap_or_default()
+            }
+            None => line.trim(),
+        };
+        if lines.is_empty() {
+            text_lines.push(line.to_string());
+        } else if text.is_empty() {
+            // Only a blank line separates paragraphs
+            if line_number.is_none() {
+                text_lines.push(String::new());
+            }
+        } else {
+            join_line(lines.last_mut().unwrap(), text);
+            match text_lines.last_mut() {
+                Some(text_line) if !text_line.is_empty() => join_line(text_line, text),
+                _ => text_lines.push(text.to_string()),
+            }
+        }
+    }
+    let line_refs = LineRefs {
+        first_line: numbers[0],
+        lines,
+    };
+    (text_lines.join("\n"), Some(line_refs))
+}
+
+fn join_line(line: &mut String, text: &str) {
+    if !line.is_empty() {
+        line.push(' ');
+    }
+    line.push_str(text);
+}
+
+fn take_line_number(section_text: &mut String, section_regex: &BillSectionRegex) -> Option<String> {
+    // Remove a line number from the end of the text, and return it
+    let start = section_text.rfind('\n').map_or(0, |index| index + 1);
+    if !is_line_number(&section_text[start..], section_regex) {
+        return None;
+    }
+    let line_number = section_text[start..].to_string();
+    section_text.truncate(start.saturating_sub(1));
+    Some(line_number)
+}
+
+fn is_line_number(text: &str, section_regex: &BillSectionRegex) -> bool {
+    // A number alone, not followed by the text of its line
+    section_regex
+        .line_number
+        .captures(text)
+        .unwrap()
+        .is_some_and(|caps| caps.get(2).is_none())
+}
+
+pub fn is_pdf_url(url: &Url) -> bool {
+    url.path().to_lowercase().ends_with(".pdf")
+}
+
+#[cfg(feature = "pdf")]
+pub fn extract_pdf_text(pdf: &[u8]) -> Result<String, SpringbokError> {
+    pdf_extract::extract_text_from_mem(pdf).map_err(|error| SpringbokError::Pdf {
+        message: format!("Cannot extract bill text from PDF: {error}"),
+        url: None,
+    })
+}
+
+#[cfg(not(feature = "pdf"))]
+pub fn extract_pdf_text(_pdf: &[u8]) -> Result<String, SpringbokError> {
+    Err(SpringbokError::Pdf {
+        message: String::from(
+            "Bill text is a PDF, which can only be read when built with the \"pdf\" feature",
+        ),
+        url: None,
+    })
 }
+
+pub fn get_bill_text_nodes_from_pdf_text(pdf_text: &str) -> Vec<String> {
+    // PDF text is wrapped to the page, so join the lines of each paragraph, and start a new
+    // paragraph at each blank line and each bill section
+    let mut text_nodes: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    for line in pdf_text.lines().map(str::trim) {
+        if (line.is_empty() || line.starts_with("SECTION ")) && !paragraph.is_empty() {
+            text_nodes.push(paragraph.join(" "));
+            paragraph.clear();
+        }
+        if !line.is_empty() {
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        text_nodes.push(paragraph.join(" "));
+    }
+    text_nodes
+}
+
+fn find_first_bill_section(
+    text_nodes: &[String],
+    section_regex: &BillSectionRegex,
+) -> Option<usize> {
+    text_nodes.iter().position(|text_node| {
+        section_regex
+            .bill_section_start
+            .is_match(text_node)
+            .unwrap()
+    })
+}
+
+pub fn collect_bill_preamble(
+    text_nodes: &[String],
+    section_regex: &BillSectionRegex,
+) -> Option<String> {
+    // Text before the first section, when there is one, is a preamble, not part of a section
+    let first_section = find_first_bill_section(text_nodes, section_regex)?;
+    let preamble = text_nodes[..first_section].join("\n").trim().to_string();
+    Some(preamble).filter(|preamble| !preamble.is_empty())
+}
+
 pub fn collect_bill_sections(
-    text_nodes: &Vec<String>,
+    text_nodes: &[String],
     section_regex: &BillSectionRegex,
 ) -> Vec<BillSection> {
     let mut bill = Vec::new();
     let mut section_text = String::new();
 
-    for text_node in text_nodes {
+    // Skip any preamble, but a bill without sections is a single section, and keep the line
+    // number of the first section heading
+    let mut first_section = find_first_bill_section(text_nodes, section_regex).unwrap_or(0);
+    if first_section > 0 && is_line_number(&text_nodes[first_section - 1], section_regex) {
+        first_section -= 1;
+    }
+    for text_node in &text_nodes[first_section..] {
         let text_str = text_node.as_str();
-        if section_regex.bill_section.is_match(text_str).unwrap() {
+        // Nodes are already joined by newlines, so whitespace-only nodes only add blank lines
+        if text_str.trim().is_empty() {
+            continue;
+        }
+        if section_regex.bill_section_start.is_match(text_str).unwrap() {
+            // A line number before a section heading numbers the heading, so carry it over
+            let line_number = take_line_number(&mut section_text, section_regex);
+
             // Indicates section_text is a complete section of bill
             if !section_text.is_empty() {
                 // Collect bill section
                 collect_bill_section(&section_text, section_regex, &mut bill);
             }
             section_text.clear();
+            if let Some(line_number) = line_number {
+                section_text.push_str(&line_number);
+            }
         }
         if section_text.is_empty() {
             section_text.push_str(text_str);
+        } else if text_str.trim_start().starts_with('.')
+            && section_regex
+                .bill_section_heading
+                .is_match(section_text.lines().last().unwrap_or_default())
+                .unwrap()
+        {
+            // The period after a section number may land on the next node, such as "SECTION 5"
+            // and ".", so rejoin them
+            section_text.push_str(text_str.trim_start());
         } else {
-            section_text.push_str(&*format!("\n{}", text_str))
+            section_text.push_str(&format!("\n{}", text_str))
         }
     }
     // Collect final bill section
@@ -90,27 +433,61 @@ pub fn collect_bill_sections(
     bill
 }
 
+fn normalize_section_text(section_str: &str) -> String {
+    // Trim each line's trailing whitespace, and keep at most one blank line between paragraphs
+    let mut lines: Vec<&str> = Vec::new();
+    for line in section_str.lines().map(str::trim_end) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
 fn collect_bill_section(
-    section_text: &String,
+    section_str: &str,
     section_regex: &BillSectionRegex,
     bill: &mut Vec<BillSection>,
 ) {
-    let section_str = section_text.as_str();
+    let (section_str, line_refs) = extract_line_refs(section_str, section_regex);
+    let section_str = &normalize_section_text(&section_str);
     let mut section_number = String::from("");
     if let Some(caps) = section_regex.bill_section.captures(section_str).unwrap() {
         section_number = String::from(&caps[1]);
+    } else if section_regex
+        .bill_section_start
+        .is_match(section_str)
+        .unwrap()
+    {
+        // Keep a section with a malformed or missing number separate, rather than merging it
+        let first_line = section_str.lines().next().unwrap_or_default();
+        warn!("Cannot find section number in {first_line:?}");
     } else {
-        println!("{section_str}");
+        debug!("Cannot find section number in {section_str:?}");
     }
     let law_sections = collect_law_sections(&section_number, section_str);
+
+    // A section which amends or repeals the General Laws should reference at least one law
+    // section
+    let unresolved = law_sections.law_source == LawSource::GeneralLaws
+        && law_sections.section_numbers.is_empty()
+        && (section_regex.amended.is_match(section_str).unwrap()
+            || section_regex.repealed.is_match(section_str).unwrap());
     let bill_section = BillSection {
         section_number,
-        text: section_text.to_string(),
+        text: section_str.to_string(),
         law_sections,
+        unresolved,
+        line_refs,
     };
     bill.push(bill_section)
 }
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SectionCounts {
     pub total: i32,
     pub amending: i32,
@@ -118,6 +495,8 @@ pub struct SectionCounts {
     pub amending_by_inserting: i32,
     pub amending_by_striking_and_inserting: i32,
     pub repealing: i32,
+    pub effective_date: i32,
+    pub appropriation: i32,
     pub other: i32,
 }
 
@@ -129,56 +508,186 @@ fn init_section_counts() -> SectionCounts {
         amending_by_inserting: 0,
         amending_by_striking_and_inserting: 0,
         repealing: 0,
+        effective_date: 0,
+        appropriation: 0,
         other: 0,
     }
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BillSectionType {
+    AmendingByStrikingAndInserting,
+    AmendingByStriking,
+    AmendingByInserting,
+    Amending,
+    Repealing,
+    EffectiveDate,
+    Appropriation,
+    Other,
+}
+
+impl BillSectionType {
+    pub fn bucket(&self) -> &'static str {
+        // Name the section counts field the type is counted in
+        match self {
+            BillSectionType::AmendingByStrikingAndInserting => "amending_by_striking_and_inserting",
+            BillSectionType::AmendingByStriking => "amending_by_striking",
+            BillSectionType::AmendingByInserting => "amending_by_inserting",
+            BillSectionType::Amending => "amending",
+            BillSectionType::Repealing => "repealing",
+            BillSectionType::EffectiveDate => "effective_date",
+            BillSectionType::Appropriation => "appropriation",
+            BillSectionType::Other => "other",
+        }
+    }
+}
+
+pub fn classify_bill_section(
+    bill_section: &BillSection,
+    section_regex: &BillSectionRegex,
+) -> BillSectionType {
+    if section_regex.amended.is_match(&bill_section.text).unwrap() {
+        // Section amends an existing law
+        let is_striking = section_regex.striking.is_match(&bill_section.text).unwrap();
+        let is_inserting = section_regex
+            .inserting
+            .is_match(&bill_section.text)
+            .unwrap();
+        if is_striking && is_inserting {
+            // Section strikes out and inserts
+            BillSectionType::AmendingByStrikingAndInserting
+        } else if is_striking {
+            // Section strikes out only
+            BillSectionType::AmendingByStriking
+        } else if is_inserting {
+            // Section inserts only
+            BillSectionType::AmendingByInserting
+        } else {
+            warn!("NOT striking or inserting: {}", &*bill_section.text);
+            BillSectionType::Amending
+        }
+    } else if section_regex.repealed.is_match(&bill_section.text).unwrap() {
+        // Section repeals an existing law
+        BillSectionType::Repealing
+    } else if section_regex
+        .effective_date
+        .is_match(&bill_section.text)
+        .unwrap()
+    {
+        // Section sets when the act takes effect
+        BillSectionType::EffectiveDate
+    } else if bill_section
+        .text
+        .lines()
+        .any(|line| section_regex.appropriation.is_match(line).unwrap())
+    {
+        // Section appropriates funds, or lists amounts in a table
+        BillSectionType::Appropriation
+    } else {
+        BillSectionType::Other
+    }
+}
+
 pub fn count_bill_section_types(
-    bill: &Vec<BillSection>,
+    bill: &[BillSection],
     section_regex: &BillSectionRegex,
 ) -> SectionCounts {
     let mut section_counts = init_section_counts();
     section_counts.total = bill.len() as i32;
     for bill_section in bill {
-        // println!("Bill Section: {:?}", bill_section);
-        if section_regex.amended.is_match(&*bill_section.text).unwrap() {
-            // Section amends an existing law
-            section_counts.amending += 1;
-            let is_striking = section_regex
-                .striking
-                .is_match(&*bill_section.text)
-                .unwrap();
-            let is_inserting = section_regex
-                .inserting
-                .is_match(&*bill_section.text)
-                .unwrap();
-            if is_striking && is_inserting {
-                // Section strikes out and inserts
+        match classify_bill_section(bill_section, section_regex) {
+            BillSectionType::AmendingByStrikingAndInserting => {
+                section_counts.amending += 1;
                 section_counts.amending_by_striking_and_inserting += 1;
-            } else if is_striking {
-                // Section strikes out only
+            }
+            BillSectionType::AmendingByStriking => {
+                section_counts.amending += 1;
                 section_counts.amending_by_striking += 1;
-            } else if is_inserting {
-                // Section inserts only
+            }
+            BillSectionType::AmendingByInserting => {
+                section_counts.amending += 1;
                 section_counts.amending_by_inserting += 1;
-            } else {
-                println!("NOT striking or inserting: {}", &*bill_section.text);
             }
+            BillSectionType::Amending => section_counts.amending += 1,
+            BillSectionType::Repealing => section_counts.repealing += 1,
+            BillSectionType::EffectiveDate => section_counts.effective_date += 1,
+            BillSectionType::Appropriation => section_counts.appropriation += 1,
+            BillSectionType::Other => section_counts.other += 1,
+        }
+    }
+    section_counts
+}
+
+pub fn format_bill_sections(bill: &[BillSection], section_regex: &BillSectionRegex) -> String {
+    // Give each section's number, type, and the law sections it references, one per line
+    let mut dump = String::new();
+    for bill_section in bill {
+        let law_sections = &bill_section.law_sections;
+        let law_reference = if bill_section.law_sections.law_source != LawSource::GeneralLaws {
+            format!(
+                "{}, not downloaded",
+                bill_section.law_sections.law_source.description()
+            )
+        } else if law_sections.section_numbers.is_empty() {
+            String::from("no law sections")
         } else {
-            let is_repealing = section_regex
-                .repealed
-                .is_match(&*bill_section.text)
-                .unwrap();
-            // Section repeals an existing law
-            if is_repealing {
-                section_counts.repealing += 1;
+            format!(
+                "chapter {}, sections {}",
+                law_sections.chapter_number,
+                law_sections.section_numbers.join(", ")
+            )
+        };
+        dump.push_str(&format!(
+            "SECTION {} — {} — {law_reference}{}\n",
+            bill_section.section_number,
+            classify_bill_section(bill_section, section_regex).bucket(),
+            if bill_section.unresolved {
+                " — unresolved"
             } else {
-                section_counts.other += 1;
+                ""
             }
-        }
+        ));
     }
-    section_counts
+    dump
 }
-pub fn print_bill_section_types(section_counts: SectionCounts) -> () {
+pub fn report_unresolved_bill_sections(bill: &[BillSection]) {
+    // Summarize the sections which amend or repeal a law that could not be identified
+    let unresolved: Vec<String> = bill
+        .iter()
+        .filter(|bill_section| bill_section.unresolved)
+        .map(|bill_section| format!("SECTION {}", bill_section.section_number))
+        .collect();
+    if !unresolved.is_empty() {
+        warn!(
+            "{} of {} sections had unparseable law references: {}",
+            unresolved.len(),
+            bill.len(),
+            unresolved.join(", ")
+        );
+    }
+
+    // Note the sections which amend laws other than the General Laws, which aren't marked up
+    let other_laws: Vec<String> = bill
+        .iter()
+        .filter(|bill_section| bill_section.law_sections.law_source != LawSource::GeneralLaws)
+        .map(|bill_section| {
+            format!(
+                "SECTION {} ({})",
+                bill_section.section_number,
+                bill_section.law_sections.law_source.description()
+            )
+        })
+        .collect();
+    if !other_laws.is_empty() {
+        warn!(
+            "{} of {} sections reference laws other than the General Laws, so are not downloaded: {}",
+            other_laws.len(),
+            bill.len(),
+            other_laws.join(", ")
+        );
+    }
+}
+
+pub fn print_bill_section_types(section_counts: SectionCounts) {
     println!("Total sections: {}", section_counts.total);
     println!("Amending sections: {}", section_counts.amending);
     println!(
@@ -194,5 +703,7 @@ pub fn print_bill_section_types(section_counts: SectionCounts) -> () {
         section_counts.amending_by_inserting
     );
     println!("Repealing sections: {}", section_counts.repealing);
+    println!("Effective date sections: {}", section_counts.effective_date);
+    println!("Appropriation sections: {}", section_counts.appropriation);
     println!("Other sections: {}", section_counts.other);
 }
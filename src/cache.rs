@@ -0,0 +1,47 @@
+use log::{debug, warn};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub cache_dir: PathBuf,
+    pub ttl: Duration,
+    pub refresh: bool,
+}
+
+pub fn read_cache(cache: &Cache, cache_name: &str) -> Option<String> {
+    // Skip the cache entirely when refreshing
+    if cache.refresh {
+        return None;
+    }
+    let cache_path = cache.cache_dir.join(cache_name);
+
+    // Only use cache files younger than the TTL
+    let modified = fs::metadata(&cache_path).ok()?.modified().ok()?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO);
+    if age > cache.ttl {
+        debug!("Cache file is stale: {}", cache_path.display());
+        return None;
+    }
+    debug!("Reading cache file: {}", cache_path.display());
+    fs::read_to_string(&cache_path).ok()
+}
+
+pub fn write_cache(cache: &Cache, cache_name: &str, contents: &str) {
+    // Failing to write the cache only costs a download next time, so warn and continue
+    let cache_path = cache.cache_dir.join(cache_name);
+    if let Err(error) =
+        fs::create_dir_all(&cache.cache_dir).and_then(|_| fs::write(&cache_path, contents))
+    {
+        warn!(
+            "Couldn't write cache file {}: {}",
+            cache_path.display(),
+            error
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    error::SpringbokError,
+    http::{build_client, HttpClient, RateLimiter, DEFAULT_USER_AGENT},
+    law_section::LawTextSelectors,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub base_url: Url,
+    pub retries: u32,
+    pub retry_base_delay: Duration,
+    pub client: Arc<dyn HttpClient>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub law_text_selectors: LawTextSelectors,
+    pub deadline: Option<Instant>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            base_url: Url::parse("https://malegislature.gov").unwrap(),
+            retries: 3,
+            retry_base_delay: Duration::from_millis(500),
+            client: build_client(Duration::from_secs(30), DEFAULT_USER_AGENT).unwrap(),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            law_text_selectors: LawTextSelectors::default(),
+            deadline: None,
+        }
+    }
+}
+
+pub fn is_past_deadline(config: &Config) -> bool {
+    config
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+pub fn check_deadline(config: &Config, stage: &str) -> Result<(), SpringbokError> {
+    // Stop before starting a stage which the run no longer has time for
+    if is_past_deadline(config) {
+        return Err(SpringbokError::DeadlineExceeded {
+            stage: stage.to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub fn get_site_url(config: &Config, path: &str) -> Url {
+    // Join a path to the base URL, which must be valid since it was parsed as a URL
+    config.base_url.join(path).unwrap()
+}
@@ -0,0 +1,192 @@
+use crate::{
+    bill_section::{
+        get_bill_text_href, is_pdf_url, BILL_TEXT_CONTAINER_SELECTOR, BILL_TEXT_SELECTORS,
+    },
+    config::{get_site_url, Config},
+    http::fetch,
+    law_section::get_law_url,
+};
+use log::info;
+use scraper::{Html, Selector};
+use url::Url;
+
+// A law section long in the General Laws, whose page is checked
+const LAW_CHAPTER: &str = "40";
+const LAW_SECTION: &str = "7";
+
+// Search results link to each bill from the second cell of its row
+const BILL_LINK_SELECTOR: &str = "tbody tr td:nth-child(2) a";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorCheck {
+    // The page checked, such as "search page"
+    pub page: String,
+    // The element the selector finds, such as "search results"
+    pub element: String,
+    // The first selector which matched, or else each selector tried
+    pub selector: String,
+    // The number of elements matched, or why the page could not be checked
+    pub count: Result<usize, String>,
+}
+
+impl SelectorCheck {
+    pub fn passed(&self) -> bool {
+        matches!(self.count, Ok(count) if count > 0)
+    }
+}
+
+pub fn run_doctor(config: &Config) -> Vec<SelectorCheck> {
+    // Check each page a download scrapes, following the first bill listed from page to page,
+    // so a page which can't be reached is reported, instead of stopping the checks
+    let mut checks = Vec::new();
+
+    // The search page lists the refiners, and the bills found
+    let search_url = get_site_url(config, "Bills/Search?SearchTerms=&Page=1");
+    let search_body = fetch_page(config, Ok(search_url));
+    checks.extend(check_page(
+        "search page",
+        &search_body,
+        vec![
+            ("refiners", vec![String::from("div#refiners")]),
+            (
+                "refiner groups",
+                vec![String::from("div#refiners fieldset")],
+            ),
+            ("search results", vec![String::from("tbody tr")]),
+            ("bill links", vec![String::from(BILL_LINK_SELECTOR)]),
+        ],
+    ));
+
+    // The bill page links to the bill text
+    let bill_url = search_body.and_then(|body| {
+        let href =
+            select_href(&body, BILL_LINK_SELECTOR).ok_or("No bill is listed on the search page")?;
+        config
+            .base_url
+            .join(&href)
+            .map_err(|error| error.to_string())
+    });
+    let bill_body = fetch_page(config, bill_url);
+    checks.extend(check_page(
+        "bill page",
+        &bill_body,
+        vec![
+            ("bill title", vec![String::from("h2")]),
+            (
+                "bill text link",
+                BILL_TEXT_SELECTORS.map(String::from).to_vec(),
+            ),
+        ],
+    ));
+
+    // The bill text page holds the text of the bill, unless given only as a PDF
+    let text_url = bill_body.and_then(|body| {
+        let href = get_bill_text_href(&body).map_err(|error| error.to_string())?;
+        let text_url = config
+            .base_url
+            .join(href.trim())
+            .map_err(|error| error.to_string())?;
+        if is_pdf_url(&text_url) {
+            return Err(format!("The bill text is only given as a PDF: {text_url}"));
+        }
+        Ok(text_url)
+    });
+    let text_body = fetch_page(config, text_url);
+    checks.extend(check_page(
+        "bill text page",
+        &text_body,
+        vec![(
+            "bill text",
+            vec![String::from(BILL_TEXT_CONTAINER_SELECTOR)],
+        )],
+    ));
+
+    // The law section page holds the law text, found with any selectors given
+    let law_url = get_law_url(config, LAW_CHAPTER, LAW_SECTION);
+    let law_body = fetch_page(config, Ok(law_url));
+    let law_text_selectors = &config.law_text_selectors;
+    checks.extend(check_page(
+        "law section page",
+        &law_body,
+        vec![(
+            "law text",
+            law_text_selectors
+                .heading
+                .iter()
+                .chain(&law_text_selectors.container)
+                .cloned()
+                .collect(),
+        )],
+    ));
+    checks
+}
+
+fn fetch_page(config: &Config, url: Result<Url, String>) -> Result<String, String> {
+    let url = url?;
+    info!("Checking selectors for {url}");
+    fetch(config, &url).map_err(|error| error.to_string())
+}
+
+fn select_href(html: &str, selector: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(selector).ok()?;
+    document
+        .select(&selector)
+        .find_map(|element| element.value().attr("href"))
+        .map(String::from)
+}
+
+fn check_page(
+    page: &str,
+    body: &Result<String, String>,
+    elements: Vec<(&str, Vec<String>)>,
+) -> Vec<SelectorCheck> {
+    let document = body.as_ref().map(|body| Html::parse_document(body));
+    elements
+        .into_iter()
+        .map(|(element, selectors)| {
+            let (selector, count) = match &document {
+                Ok(document) => count_matches(document, &selectors),
+                Err(error) => (selectors.join(", "), Err(error.to_string())),
+            };
+            SelectorCheck {
+                page: page.to_string(),
+                element: element.to_string(),
+                selector,
+                count,
+            }
+        })
+        .collect()
+}
+
+fn count_matches(document: &Html, selectors: &[String]) -> (String, Result<usize, String>) {
+    // Report the first selector which matches, since the others are only fallbacks
+    for selector in selectors {
+        let count = match Selector::parse(selector) {
+            Ok(parsed_selector) => document.select(&parsed_selector).count(),
+            Err(error) => return (selector.clone(), Err(format!("Invalid selector: {error}"))),
+        };
+        if count > 0 {
+            return (selector.clone(), Ok(count));
+        }
+    }
+    (selectors.join(", "), Ok(0))
+}
+
+pub fn format_selector_checks(checks: &[SelectorCheck]) -> String {
+    // One line per selector, passing if it matched any element
+    let mut output = String::new();
+    for check in checks {
+        let status = if check.passed() { "PASS" } else { "FAIL" };
+        let result = match &check.count {
+            Ok(0) => String::from("matched no elements"),
+            Ok(count) => format!("matched {count} element(s)"),
+            Err(error) => format!("not checked: {error}"),
+        };
+        output.push_str(&format!(
+            "{status} {}: {} ({}) {result}\n",
+            check.page, check.element, check.selector
+        ));
+    }
+    output
+}
@@ -0,0 +1,164 @@
+use std::{error::Error, fmt, io, path::PathBuf, process::ExitStatus};
+use url::Url;
+
+#[derive(Debug)]
+pub enum SpringbokError {
+    /// A request failed, after any retries, with the HTTP status, if the server responded
+    Network {
+        url: String,
+        status: Option<u16>,
+        message: String,
+    },
+    /// No element matched any of the selectors, usually since the site layout changed
+    Selector {
+        element: String,
+        selectors: Vec<String>,
+        url: Option<String>,
+    },
+    /// A page, bill number, URL, or selector could not be parsed
+    Parse { message: String },
+    /// An argument, such as a refiner value or filing date, can't be used
+    InvalidArgument { message: String },
+    /// The text of a PDF could not be extracted
+    Pdf {
+        message: String,
+        url: Option<String>,
+    },
+    /// A file or folder could not be read or written, or a program could not be run
+    Io { path: PathBuf, source: io::Error },
+    /// The site has no such law section
+    LawSectionNotFound {
+        law_chapter: String,
+        law_section: String,
+        url: String,
+    },
+    /// The asciidoctor executable could not be found
+    AsciidoctorMissing { program: String },
+    /// Asciidoctor failed to render a file, with any messages it printed
+    Asciidoctor {
+        program: String,
+        file: PathBuf,
+        status: ExitStatus,
+        messages: Option<String>,
+    },
+    /// The deadline passed before the stage could start
+    DeadlineExceeded { stage: String },
+}
+
+impl SpringbokError {
+    pub fn parse(message: impl Into<String>) -> Self {
+        SpringbokError::Parse {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        SpringbokError::InvalidArgument {
+            message: message.into(),
+        }
+    }
+
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        SpringbokError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Add the URL of the page searched to a selector miss, or of the PDF read
+    pub fn with_url(self, page_url: &Url) -> Self {
+        match self {
+            SpringbokError::Selector {
+                element,
+                selectors,
+                url: None,
+            } => SpringbokError::Selector {
+                element,
+                selectors,
+                url: Some(page_url.to_string()),
+            },
+            SpringbokError::Pdf { message, url: None } => SpringbokError::Pdf {
+                message,
+                url: Some(page_url.to_string()),
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for SpringbokError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpringbokError::Network { url, message, .. } => {
+                write!(f, "Couldn't get {url}: {message}")
+            }
+            SpringbokError::Selector {
+                element,
+                selectors,
+                url,
+            } => {
+                let selectors: Vec<String> = selectors
+                    .iter()
+                    .map(|selector| format!("{selector:?}"))
+                    .collect();
+                write!(
+                    f,
+                    "Cannot find {element} using selectors {}",
+                    selectors.join(", ")
+                )?;
+                if let Some(url) = url {
+                    write!(f, " for {url}")?;
+                }
+                Ok(())
+            }
+            SpringbokError::Parse { message } => write!(f, "{message}"),
+            SpringbokError::InvalidArgument { message } => write!(f, "{message}"),
+            SpringbokError::Pdf { message, url } => {
+                write!(f, "{message}")?;
+                if let Some(url) = url {
+                    write!(f, " for {url}")?;
+                }
+                Ok(())
+            }
+            SpringbokError::Io { path, source } => {
+                write!(f, "Couldn't use {}: {source}", path.display())
+            }
+            SpringbokError::LawSectionNotFound {
+                law_chapter,
+                law_section,
+                url,
+            } => write!(
+                f,
+                "Cannot find section {law_section} of chapter {law_chapter} at {url}"
+            ),
+            SpringbokError::AsciidoctorMissing { program } => write!(
+                f,
+                "Could not find {program} - install asciidoctor (i.e. ~brew install asciidoctor), or skip rendering with --no-asciidoctor"
+            ),
+            SpringbokError::Asciidoctor {
+                program,
+                file,
+                status,
+                messages,
+            } => {
+                write!(f, "{program} failed with {status}: ")?;
+                match messages {
+                    Some(messages) => write!(f, "{messages}"),
+                    None => write!(f, "{}", file.display()),
+                }
+            }
+            SpringbokError::DeadlineExceeded { stage } => {
+                write!(f, "Deadline exceeded before {stage}")
+            }
+        }
+    }
+}
+
+impl Error for SpringbokError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SpringbokError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    config::{check_deadline, Config},
+    error::SpringbokError,
+};
+use log::warn;
+use rand::Rng;
+use reqwest::{blocking::Client, StatusCode};
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+",
+    env!("CARGO_PKG_REPOSITORY"),
+    ")"
+);
+
+// Requests per second, which is polite to the site, but still lets downloads run in parallel
+pub const DEFAULT_RATE_LIMIT: f64 = 4.0;
+
+pub trait HttpClient: fmt::Debug + Send + Sync {
+    /// Get the body of the page at the URL, in a single attempt
+    fn get(&self, url: &Url) -> Result<String, Box<dyn Error>>;
+
+    /// Get the body at the URL as bytes, such as a PDF, in a single attempt
+    fn get_bytes(&self, url: &Url) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.get(url).map(String::into_bytes)
+    }
+}
+
+impl HttpClient for Client {
+    fn get(&self, url: &Url) -> Result<String, Box<dyn Error>> {
+        Ok(Client::get(self, url.clone())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())?)
+    }
+
+    fn get_bytes(&self, url: &Url) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(Client::get(self, url.clone())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())?
+            .to_vec())
+    }
+}
+
+pub fn build_client(
+    timeout: Duration,
+    user_agent: &str,
+) -> Result<Arc<dyn HttpClient>, reqwest::Error> {
+    // Share one client, so connections are reused, no request can hang indefinitely, and
+    // every request identifies this tool
+    let client = Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .build()?;
+    Ok(Arc::new(client))
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_request: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Allow at most this many requests per second, or any number if zero
+    pub fn new(requests_per_second: f64) -> Result<Self, Box<dyn Error>> {
+        let interval = if requests_per_second == 0.0 {
+            Duration::ZERO
+        } else if requests_per_second > 0.0 {
+            Duration::try_from_secs_f64(1.0 / requests_per_second)?
+        } else {
+            return Err(format!("Invalid rate limit: {requests_per_second}").into());
+        };
+        Ok(RateLimiter {
+            interval,
+            next_request: Mutex::new(Instant::now()),
+        })
+    }
+
+    pub fn reserve(&self) -> Duration {
+        // Claim the next free slot, and return how long to wait for it, without holding the lock
+        // while waiting, so threads queue up one interval apart
+        let mut next_request = self.next_request.lock().unwrap();
+        let now = Instant::now();
+        let slot = (*next_request).max(now);
+        *next_request = slot + self.interval;
+        slot - now
+    }
+
+    pub fn wait(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(DEFAULT_RATE_LIMIT).unwrap()
+    }
+}
+
+pub fn fetch(config: &Config, url: &Url) -> Result<String, SpringbokError> {
+    fetch_with_retries(config, url, |client| client.get(url))
+}
+
+pub fn fetch_bytes(config: &Config, url: &Url) -> Result<Vec<u8>, SpringbokError> {
+    fetch_with_retries(config, url, |client| client.get_bytes(url))
+}
+
+fn fetch_with_retries<T>(
+    config: &Config,
+    url: &Url,
+    get: impl Fn(&dyn HttpClient) -> Result<T, Box<dyn Error>>,
+) -> Result<T, SpringbokError> {
+    // Get the body, retrying transient failures with exponential backoff
+    let mut attempt = 0;
+    loop {
+        check_deadline(config, &format!("getting {url}"))?;
+        config.rate_limiter.wait();
+        let error = match get(config.client.as_ref()) {
+            Ok(body) => return Ok(body),
+            Err(error) => error,
+        };
+        let reqwest_error = error.downcast_ref::<reqwest::Error>();
+        if attempt >= config.retries || !reqwest_error.is_some_and(is_retryable) {
+            return Err(SpringbokError::Network {
+                url: url.to_string(),
+                status: reqwest_error
+                    .and_then(reqwest::Error::status)
+                    .map(|status| status.as_u16()),
+                message: error.to_string(),
+            });
+        }
+        let delay = get_retry_delay(config.retry_base_delay, attempt);
+        warn!("Couldn't get {url}: {error}; retrying in {delay:?}");
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+#[cfg(feature = "async")]
+pub fn build_async_client(
+    timeout: Duration,
+    user_agent: &str,
+) -> Result<reqwest::Client, reqwest::Error> {
+    // Configure the non-blocking client the same way as the blocking one
+    reqwest::Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .build()
+}
+
+#[cfg(feature = "async")]
+pub async fn fetch_async(
+    config: &Config,
+    client: &reqwest::Client,
+    url: &Url,
+) -> Result<String, SpringbokError> {
+    // Get the page body without blocking, retrying as fetch does
+    let mut attempt = 0;
+    loop {
+        check_deadline(config, &format!("getting {url}"))?;
+        tokio::time::sleep(config.rate_limiter.reserve()).await;
+        let result = match client.get(url.clone()).send().await {
+            Ok(response) => match response.error_for_status() {
+                Ok(response) => response.text().await,
+                Err(error) => Err(error),
+            },
+            Err(error) => Err(error),
+        };
+        let error = match result {
+            Ok(body) => return Ok(body),
+            Err(error) => error,
+        };
+        if attempt >= config.retries || !is_retryable(&error) {
+            return Err(SpringbokError::Network {
+                url: url.to_string(),
+                status: error.status().map(|status| status.as_u16()),
+                message: error.to_string(),
+            });
+        }
+        let delay = get_retry_delay(config.retry_base_delay, attempt);
+        warn!("Couldn't get {url}: {error}; retrying in {delay:?}");
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+pub fn is_retryable(error: &reqwest::Error) -> bool {
+    // Retry server errors, timeouts, and connection errors, but not client errors, such as 404
+    match error.status() {
+        Some(status) => is_retryable_status(status),
+        None => error.is_timeout() || error.is_connect() || error.is_request() || error.is_body(),
+    }
+}
+
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+pub fn get_retry_delay(base_delay: Duration, attempt: u32) -> Duration {
+    // Double the delay with each attempt, and add up to half again as jitter
+    let delay = base_delay.saturating_mul(2u32.saturating_pow(attempt));
+    let jitter = rand::thread_rng().gen_range(0.0..=0.5);
+    delay + delay.mul_f64(jitter)
+}
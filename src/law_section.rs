@@ -1,164 +1,687 @@
-use crate::bill_section::{BillSection, BillSectionRegex};
+#[cfg(feature = "async")]
+use crate::http::fetch_async;
+use crate::{
+    cache::{read_cache, write_cache, Cache},
+    config::{get_site_url, is_past_deadline, Config},
+    error::SpringbokError,
+    http::fetch,
+    markup::{init_punctuation_regex, normalize_punctuation, PunctuationRegex},
+};
 use fancy_regex::Regex;
-use log::info;
-use scraper::{Element, Html, Selector};
+use log::{debug, info, warn};
+use scraper::{Element, ElementRef, Html, Selector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    sync::{mpsc, mpsc::Sender},
+    cmp::Ordering,
+    fmt,
+    sync::{mpsc::Sender, Arc, Mutex, OnceLock},
     thread,
+    thread::JoinHandle,
 };
 use url::Url;
 
-pub fn get_section_key(chapter: &String, section: &String) -> String {
-    String::from(chapter.to_string() + "-" + section)
+pub fn normalize_law_number(number: &str) -> String {
+    // Trim, collapse whitespace, and upper case letter suffixes, such as "6a" to "6A", so each
+    // law chapter and section has one spelling
+    number
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+pub fn compare_law_numbers(a: &str, b: &str) -> Ordering {
+    // Compare runs of digits by value and other runs by text, so "2" < "3" < "3A" < "10", and
+    // fractions, such as "4 1/2", follow their whole number
+    let a_parts = split_law_number(a);
+    let b_parts = split_law_number(b);
+    for (a_part, b_part) in a_parts.iter().zip(&b_parts) {
+        let a_digits = a_part.starts_with(|c: char| c.is_ascii_digit());
+        let b_digits = b_part.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = match (a_digits, b_digits) {
+            (true, true) => {
+                // Compare by length, then text, so numbers of any size compare by value
+                let a_number = a_part.trim_start_matches('0');
+                let b_number = b_part.trim_start_matches('0');
+                a_number
+                    .len()
+                    .cmp(&b_number.len())
+                    .then_with(|| a_number.cmp(b_number))
+            }
+            // Numbers come before letters, such as "3 1/2" before "3A"
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => a_part.to_uppercase().cmp(&b_part.to_uppercase()),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // A number sorts before any number it prefixes, and spellings are otherwise kept distinct
+    a_parts.len().cmp(&b_parts.len()).then_with(|| a.cmp(b))
+}
+
+fn split_law_number(number: &str) -> Vec<&str> {
+    // Split into runs of digits and runs of letters, dropping whitespace and punctuation
+    let mut parts = Vec::new();
+    let mut start = None;
+    for (index, c) in number.char_indices() {
+        if let Some(part_start) = start {
+            let part: &str = &number[part_start..index];
+            let same_kind = if part.starts_with(|c: char| c.is_ascii_digit()) {
+                c.is_ascii_digit()
+            } else {
+                c.is_alphabetic()
+            };
+            if same_kind {
+                continue;
+            }
+            parts.push(part);
+            start = None;
+        }
+        if c.is_ascii_digit() || c.is_alphabetic() {
+            start = Some(index);
+        }
+    }
+    if let Some(part_start) = start {
+        parts.push(&number[part_start..]);
+    }
+    parts
+}
+
+pub fn compare_law_sections(a: &(String, String), b: &(String, String)) -> Ordering {
+    // Order by chapter, then section
+    compare_law_numbers(&a.0, &b.0).then_with(|| compare_law_numbers(&a.1, &b.1))
+}
+
+pub fn get_section_key(chapter: &str, section: &str) -> String {
+    chapter.to_string() + "-" + section
 }
+
+pub fn log_context(bill_section_number: Option<&str>, law_chapter_key: Option<&str>) -> String {
+    // Such as "[SECTION 12 / 40-7]", so each message can be traced to what produced it
+    let parts: Vec<String> = [
+        bill_section_number.map(|number| format!("SECTION {number}")),
+        law_chapter_key.map(String::from),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    format!("[{}]", parts.join(" / "))
+}
+
+pub fn download_law_sections(
+    config: &Config,
+    required_law_sections: Vec<(String, String)>,
+    jobs: usize,
+    cache: Option<Cache>,
+    tx: Sender<(String, String, Result<String, String>)>,
+) -> Vec<JoinHandle<()>> {
+    // Download law sections with at most the given number in flight at once, starting none once
+    // the deadline passes
+    let config = config.clone();
+    let deadline_config = config.clone();
+    run_bounded_until(
+        required_law_sections,
+        jobs,
+        move || is_past_deadline(&deadline_config),
+        move |(law_chapter, law_section)| {
+            download_law_section(
+                &config,
+                &law_chapter,
+                &law_section,
+                cache.as_ref(),
+                tx.clone(),
+            );
+        },
+    )
+}
+
+pub fn run_bounded<T, F>(items: Vec<T>, jobs: usize, work: F) -> Vec<JoinHandle<()>>
+where
+    T: Send + 'static,
+    F: Fn(T) + Send + Sync + 'static,
+{
+    run_bounded_until(items, jobs, || false, work)
+}
+
+pub fn run_bounded_until<T, S, F>(
+    items: Vec<T>,
+    jobs: usize,
+    is_stopped: S,
+    work: F,
+) -> Vec<JoinHandle<()>>
+where
+    T: Send + 'static,
+    S: Fn() -> bool + Send + Sync + 'static,
+    F: Fn(T) + Send + Sync + 'static,
+{
+    // Share the work queue and work across a fixed number of worker threads, each of which
+    // takes the next item from the queue until the queue is empty, or the work is stopped
+    let queue = Arc::new(Mutex::new(items.into_iter()));
+    let is_stopped = Arc::new(is_stopped);
+    let work = Arc::new(work);
+    (0..jobs.max(1))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let is_stopped = Arc::clone(&is_stopped);
+            let work = Arc::clone(&work);
+            thread::spawn(move || loop {
+                if is_stopped() {
+                    break;
+                }
+                let item = queue.lock().unwrap().next();
+                match item {
+                    Some(item) => work(item),
+                    None => break,
+                }
+            })
+        })
+        .collect()
+}
+
 pub fn download_law_section(
-    law_chapter: &String,
-    law_section: &String,
-    tx: Sender<(String, String, String)>,
+    config: &Config,
+    law_chapter: &str,
+    law_section: &str,
+    cache: Option<&Cache>,
+    tx: Sender<(String, String, Result<String, String>)>,
 ) {
-    // Clone input arguments and move into the spawned thread closure
-    let law_chapter = law_chapter.clone();
-    let law_section = law_section.clone();
-    thread::spawn(move || {
-        // Construct the law URL
-        let mut law_url = Url::parse("https://malegislature.gov/GeneralLaws/GoTo").unwrap();
-        law_url
-            .query_pairs_mut()
-            .append_pair("ChapterGoTo", law_chapter.as_str())
-            .append_pair("SectionGoTo", format_law_section(&law_section).as_str());
-        info!("Value for law URL: {}", law_url);
-
-        // Get and parse the law page
-        let body = reqwest::blocking::get(law_url.clone())
-            .unwrap()
-            .text()
-            .unwrap();
-        let document = Html::parse_document(body.as_str());
-
-        // Find the text node container
-        let h2_selector = Selector::parse("h2#skipTo").unwrap();
-        let h2_element = match document.select(&h2_selector).next() {
-            Some(element) => element,
-            None => panic!("Cannot get element for URL {}", law_url),
-        };
-        let container_element = h2_element.parent_element().unwrap();
+    // Send any error as a message so it can cross threads
+    let context = log_context(None, Some(&get_section_key(law_chapter, law_section)));
+    let law_text = fetch_law_section(config, law_chapter, law_section, cache)
+        .map_err(|error| error.to_string());
+    match &law_text {
+        Ok(_) => debug!("{context} downloaded law section"),
+        Err(message) => warn!("{context} could not download law section: {message}"),
+    }
+    // The receiver is gone if the deadline passed while downloading, so the result is not wanted
+    let _ = tx.send((law_chapter.to_string(), law_section.to_string(), law_text));
+}
+
+pub fn fetch_law_section(
+    config: &Config,
+    law_chapter: &str,
+    law_section: &str,
+    cache: Option<&Cache>,
+) -> Result<String, SpringbokError> {
+    // Use the cached law text, if present and fresh
+    let cache_name = format!("{}.txt", get_section_key(law_chapter, law_section));
+    if let Some(law_text) = cache.and_then(|cache| read_cache(cache, &cache_name)) {
+        return Ok(law_text);
+    }
+
+    // Construct the law URL
+    let law_url = get_law_url(config, law_chapter, law_section);
+    info!(
+        "[{}] law URL: {law_url}",
+        get_section_key(law_chapter, law_section)
+    );
+
+    // Get the law text
+    let body = fetch(config, &law_url)
+        .map_err(|error| law_section_not_found(error, law_chapter, law_section))?;
+    let law_text = parse_law_text(&body, &config.law_text_selectors)
+        .map_err(|error| error.with_url(&law_url))?;
+    if let Some(cache) = cache {
+        write_cache(cache, &cache_name, &law_text);
+    }
+    Ok(law_text)
+}
+
+#[cfg(feature = "async")]
+pub async fn fetch_law_section_async(
+    config: &Config,
+    client: &reqwest::Client,
+    law_chapter: &str,
+    law_section: &str,
+    cache: Option<&Cache>,
+) -> Result<String, SpringbokError> {
+    // Use the cached law text, if present and fresh
+    let cache_name = format!("{}.txt", get_section_key(law_chapter, law_section));
+    if let Some(law_text) = cache.and_then(|cache| read_cache(cache, &cache_name)) {
+        return Ok(law_text);
+    }
+
+    // Get and parse the law text without blocking, using the same URL and parser as
+    // fetch_law_section
+    let law_url = get_law_url(config, law_chapter, law_section);
+    info!(
+        "[{}] law URL: {law_url}",
+        get_section_key(law_chapter, law_section)
+    );
+    let body = fetch_async(config, client, &law_url)
+        .await
+        .map_err(|error| law_section_not_found(error, law_chapter, law_section))?;
+    let law_text = parse_law_text(&body, &config.law_text_selectors)
+        .map_err(|error| error.with_url(&law_url))?;
+    if let Some(cache) = cache {
+        write_cache(cache, &cache_name, &law_text);
+    }
+    Ok(law_text)
+}
+
+fn law_section_not_found(
+    error: SpringbokError,
+    law_chapter: &str,
+    law_section: &str,
+) -> SpringbokError {
+    // The site responds with not found for a law section which doesn't exist, or no longer does
+    match error {
+        SpringbokError::Network {
+            url,
+            status: Some(404),
+            ..
+        } => SpringbokError::LawSectionNotFound {
+            law_chapter: law_chapter.to_string(),
+            law_section: law_section.to_string(),
+            url,
+        },
+        error => error,
+    }
+}
 
-        // Collect the law text nodes
-        let mut law_text = String::new();
-        for text_node in container_element.text().collect::<Vec<_>>() {
-            law_text.push_str(text_node);
+pub fn get_law_url(config: &Config, law_chapter: &str, law_section: &str) -> Url {
+    // Pass the chapter verbatim, since chapters may be lettered, such as "6A" or "111F"
+    let mut law_url = get_site_url(config, "GeneralLaws/GoTo");
+    law_url
+        .query_pairs_mut()
+        .append_pair("ChapterGoTo", law_chapter)
+        .append_pair("SectionGoTo", format_law_section(law_section).as_str());
+    law_url
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LawTextSelectors {
+    // Each selects the law section heading, whose parent holds the law text
+    pub heading: Vec<String>,
+    // Each selects the element holding the law text, tried after the headings
+    pub container: Vec<String>,
+}
+
+impl Default for LawTextSelectors {
+    fn default() -> Self {
+        LawTextSelectors {
+            heading: vec![
+                String::from("h2#skipTo"),
+                String::from("#skipTo"),
+                String::from("main h2"),
+            ],
+            container: Vec::new(),
         }
+    }
+}
 
-        tx.send((law_chapter, law_section, law_text)).unwrap();
-    });
+pub fn parse_law_text(
+    html: &str,
+    law_text_selectors: &LawTextSelectors,
+) -> Result<String, SpringbokError> {
+    // Parse the law page
+    let document = Html::parse_document(html);
+
+    // Find the text node container, trying each selector in turn, since the page layout changes
+    let parse_selector = |selector: &String| {
+        Selector::parse(selector).map_err(|error| {
+            SpringbokError::parse(format!("Invalid selector {selector:?}: {error}"))
+        })
+    };
+    let mut container_element = None;
+    for selector in &law_text_selectors.heading {
+        container_element = document
+            .select(&parse_selector(selector)?)
+            .next()
+            .and_then(|heading_element| heading_element.parent_element());
+        if container_element.is_some() {
+            break;
+        }
+    }
+    for selector in &law_text_selectors.container {
+        if container_element.is_some() {
+            break;
+        }
+        container_element = document.select(&parse_selector(selector)?).next();
+    }
+    let container_element = container_element.ok_or_else(|| SpringbokError::Selector {
+        element: String::from("law text element"),
+        selectors: law_text_selectors
+            .heading
+            .iter()
+            .chain(&law_text_selectors.container)
+            .cloned()
+            .collect(),
+        url: None,
+    })?;
+
+    // Collect the law text a block at a time, separating blocks with a blank line, so subsections
+    // stay apart, both for the subsection regexes and in the rendered markup
+    let mut law_blocks = Vec::new();
+    let mut inline_text = String::new();
+    collect_law_blocks(container_element, &mut law_blocks, &mut inline_text);
+    push_law_block(&mut law_blocks, &mut inline_text);
+    Ok(law_blocks.join("\n\n"))
+}
+
+// Elements which start a new block of law text, rather than continuing the current one
+const LAW_BLOCK_ELEMENTS: [&str; 20] = [
+    "address",
+    "article",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ol",
+    "p",
+    "section",
+    "table",
+    "tr",
+];
+
+fn collect_law_blocks(element: ElementRef, law_blocks: &mut Vec<String>, inline_text: &mut String) {
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            inline_text.push_str(text);
+        } else if let Some(child_element) = ElementRef::wrap(child) {
+            let name = child_element.value().name();
+            if name == "script" || name == "style" {
+                continue;
+            }
+            let is_block = LAW_BLOCK_ELEMENTS.contains(&name);
+            if is_block {
+                push_law_block(law_blocks, inline_text);
+            }
+            collect_law_blocks(child_element, law_blocks, inline_text);
+            if is_block {
+                push_law_block(law_blocks, inline_text);
+            }
+        }
+    }
+}
+
+fn push_law_block(law_blocks: &mut Vec<String>, inline_text: &mut String) {
+    // Collapse the whitespace of an inline run, which is only source formatting
+    let law_block = inline_text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !law_block.is_empty() {
+        law_blocks.push(law_block);
+    }
+    inline_text.clear();
+}
+
+#[derive(Debug, Clone)]
+pub struct LawSectionFailure {
+    pub law_chapter: String,
+    pub law_section: String,
+    pub message: String,
 }
+
+impl fmt::Display for LawSectionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "chapter {} section {} failed: {}",
+            self.law_chapter, self.law_section, self.message
+        )
+    }
+}
+
 pub struct LawSectionWithText {
-    pub(crate) law_chapter_key: String,
-    pub(crate) text: String,
-    pub(crate) bill_section_keys: Vec<String>,
+    pub law_chapter_key: String,
+    pub text: String,
+    pub bill_section_keys: Vec<String>,
+    pub line_index: Option<Vec<String>>,
+}
+
+pub fn index_law_lines(law_text: &str, law_section_regex: &LawSectionRegex) -> Vec<String> {
+    // Split law text into numbered lines at newlines and sentence boundaries, since the
+    // online version of the law carries no line numbers of its own
+    let mut law_lines = Vec::new();
+    for line in law_text.lines() {
+        let mut start = 0;
+        for boundary in law_section_regex.sentence.find_iter(line) {
+            let boundary = boundary.expect("Bad Regex");
+            law_lines.push(line[start..boundary.start()].trim().to_string());
+            start = boundary.end();
+        }
+        law_lines.push(line[start..].trim().to_string());
+    }
+    law_lines.retain(|law_line| !law_line.is_empty());
+    law_lines
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LawSections {
     pub chapter_number: String,
     pub section_numbers: Vec<String>,
+    pub law_source: LawSource,
 }
 #[derive(Debug, Clone)]
 pub struct LawSectionRegex {
     law_chapter: Regex,
     law_section: Regex,
     section_list: Regex,
+    section_range: Regex,
+    list_delimiter: Regex,
+    session_laws: Regex,
+    sentence: Regex,
+    punctuation: PunctuationRegex,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LawSource {
+    /// A chapter of the General Laws, whose sections can be downloaded
+    #[default]
+    GeneralLaws,
+    /// A chapter of the acts or resolves of a year, such as "chapter 123 of the acts of 2022"
+    SessionLaws,
+    /// An article of the Constitution, or of its Amendments
+    Constitution,
+}
+
+impl LawSource {
+    pub fn description(&self) -> &'static str {
+        match self {
+            LawSource::GeneralLaws => "the General Laws",
+            LawSource::SessionLaws => "the session laws",
+            LawSource::Constitution => "the Constitution",
+        }
+    }
 }
 
 pub fn init_law_section_regex() -> LawSectionRegex {
     LawSectionRegex {
         law_chapter: Regex::new(
-            r"^\s*(?i)section [\d+][^a-z](?-i)\.*[^\.:-]*?[cC]hapter\s*(\d*\w*)",
+            r"^(\s*(?i)section [\d+][^a-z](?-i)\.*)?[^\.:-]*?[cC]hapter\s*(\d*\w*)",
         )
         .unwrap(),
         law_section: Regex::new(
-            r"^\s*(?i)section [\d+][^a-z](?-i)\.*[^\.:-]*?([sS]ection[s]*)\s*(\d*\w*)",
+            r"^(\s*(?i)section [\d+][^a-z](?-i)\.*)?[^\.:-]*?([sS]ection[s]*)\s*(\d*\w*(?:[\u00BC-\u00BE\u2150-\u215E]\w*)?)",
+        )
+        .unwrap(),
+        section_list: Regex::new(
+            r"[sS]ections\s+(\d+\w*\s*[\u00BC-\u00BE\u2150-\u215E]*(\s*(,|\band\b|&)\s*\d+\w*\s*[\u00BC-\u00BE\u2150-\u215E]*)*)",
+        )
+        .unwrap(),
+        section_range: Regex::new(r"[sS]ections\s+(\d+\w*)\s+(?:to|through)\s+(\d+\w*)").unwrap(),
+        list_delimiter: Regex::new(r"\s*(,|\band\b|&)\s*").unwrap(),
+        // Matched against the reference from its first chapter
+        session_laws: Regex::new(
+            r"(?i)^chapter[ \t\n]+[0-9]+[A-Z0-9]*,?[ \t\n]+of[ \t\n]+the[ \t\n]+(?:special[ \t\n]+)?(?:acts|resolves)(?:[^A-Z]|$)",
         )
         .unwrap(),
-        section_list: Regex::new(r"(\d+\w*\s*[\u00BC-\u00BE\u2150-\u215E]*)[,\s]").unwrap(),
+        sentence: Regex::new(r"(?<=[.;:])\s+(?=[A-Z(])").unwrap(),
+        punctuation: init_punctuation_regex(),
     }
 }
-pub fn collect_law_sections(_bill_section_number: &str, section_str: &str) -> LawSections {
+
+fn get_law_source(section_str: &str, law_section_regex: &LawSectionRegex) -> LawSource {
+    // Distinguish the General Laws from other laws a bill section may amend, which can't be
+    // downloaded by chapter and section. Only the reference before any colon, and its first
+    // chapter, name the law amended.
+    let reference = section_str.split(':').next().unwrap_or_default();
+    let chapter_start = reference
+        .to_ascii_lowercase()
+        .find("chapter")
+        .unwrap_or(reference.len());
+    if law_section_regex
+        .session_laws
+        .is_match(&reference[chapter_start..])
+        .unwrap()
+    {
+        LawSource::SessionLaws
+    } else if reference[..chapter_start]
+        .to_ascii_lowercase()
+        .contains("constitution")
+    {
+        LawSource::Constitution
+    } else {
+        LawSource::GeneralLaws
+    }
+}
+
+pub fn collect_law_sections(bill_section_number: &str, section_str: &str) -> LawSections {
+    let context = log_context(Some(bill_section_number), None);
     // Init section regex
     let law_section_regex = init_law_section_regex();
+    let section_str = &normalize_punctuation(section_str, &law_section_regex.punctuation);
+    // Skip laws other than the General Laws, which have no sections to download
+    let law_source = get_law_source(section_str, &law_section_regex);
+    if law_source != LawSource::GeneralLaws {
+        debug!("{context} references {}", law_source.description());
+        return LawSections {
+            chapter_number: String::new(),
+            section_numbers: Vec::new(),
+            law_source,
+        };
+    }
     // Capture law chapter
     let mut law_chapter = String::from("");
     if let Some(caps) = law_section_regex.law_chapter.captures(section_str).unwrap() {
-        law_chapter = String::from(&caps[1]);
+        law_chapter = String::from(&caps[2]);
     } else {
         //TODO: Handle this as error instead
-        println!("{section_str}");
+        debug!("{context} cannot find law chapter in {section_str:?}");
     }
     // Exit if no chapter found
-    if law_chapter == "" {
+    if law_chapter.is_empty() {
         return LawSections {
             chapter_number: law_chapter,
             section_numbers: Vec::new(),
+            law_source,
         };
     }
     // Capture law sections
     let mut law_sections: Vec<String> = Vec::new();
     if let Some(caps) = law_section_regex.law_section.captures(section_str).unwrap() {
-        if caps[1].trim().to_lowercase().eq("section") {
+        if caps[2].trim().to_lowercase().eq("section") {
             // Found a single section
-            law_sections.push(String::from((&caps[2]).trim_end()));
-        } else if caps[1].trim().to_lowercase().eq("sections") {
-            // Found multiple, comma delimited sections
-            let mut sections: Vec<_> = law_section_regex
+            law_sections.push(String::from(caps[3].trim_end()));
+        } else if caps[2].trim().to_lowercase().eq("sections") {
+            if let Some(range_caps) = law_section_regex
+                .section_range
+                .captures(section_str)
+                .unwrap()
+            {
+                // Found a range of sections
+                let mut sections = expand_section_range(&range_caps[1], &range_caps[2]);
+                law_sections.append(&mut sections);
+            } else if let Some(list_caps) = law_section_regex
                 .section_list
-                .find_iter(section_str)
-                .map(|m| m.expect("Bad Regex").as_str())
-                .map(|s| s.trim_end_matches(",").trim_end())
-                .map(|s| String::from(s))
-                .collect();
-            law_sections.append(&mut sections);
+                .captures(section_str)
+                .unwrap()
+            {
+                // Found multiple sections, delimited by commas, "and", or "&"
+                let mut sections: Vec<_> = law_section_regex
+                    .list_delimiter
+                    .replace_all(&list_caps[1], ",")
+                    .split(',')
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect();
+                law_sections.append(&mut sections);
+            }
         } else {
             //TODO: Handle this as error instead
-            println!("{section_str}");
+            debug!("{context} cannot find law section in {section_str:?}");
         }
     } else {
         //TODO: Handle this as error instead
-        println!("{section_str}");
+        debug!("{context} cannot find law section in {section_str:?}");
     }
-    println!("{:?}, {}", law_sections, law_chapter);
+    debug!("{context} chapter {law_chapter}, sections {law_sections:?}");
     LawSections {
         chapter_number: law_chapter,
         section_numbers: law_sections,
+        law_source,
+    }
+}
+
+pub fn expand_section_range(start_section: &str, end_section: &str) -> Vec<String> {
+    // Enumerate each section in a numeric range, but keep lettered sections as single entries
+    match (start_section.parse::<u32>(), end_section.parse::<u32>()) {
+        (Ok(start), Ok(end)) if start <= end => (start..=end).map(|s| s.to_string()).collect(),
+        _ => {
+            warn!("Cannot expand section range {start_section} to {end_section}");
+            vec![start_section.to_string(), end_section.to_string()]
+        }
+    }
+}
+
+pub fn format_law_section(law_section: &str) -> String {
+    // Format law sections containing unicode vulgar fractions, wherever they appear, for use in
+    // going to law section, such as "3¾A" as "3 3~4A"
+    let mut formatted_law_section = String::new();
+    for c in law_section.chars() {
+        match get_fraction_parts(c) {
+            Some((numerator, denominator)) => {
+                formatted_law_section.truncate(formatted_law_section.trim_end().len());
+                formatted_law_section.push_str(&format!(" {numerator}~{denominator}"));
+            }
+            None => formatted_law_section.push(c),
+        }
     }
+
+    // Format fractions written with a slash, such as "5 1/2", the same way, compiling the regex
+    // only once, since a law URL has no law section regex at hand
+    static SLASH_FRACTION: OnceLock<Regex> = OnceLock::new();
+    SLASH_FRACTION
+        .get_or_init(|| Regex::new(r"\s*(\d+)/(\d+)").unwrap())
+        .replace_all(&formatted_law_section, " $1~$2")
+        .into_owned()
 }
 
-pub fn format_law_section(law_section: &String) -> String {
-    // Format law sections containing unicode vulgar fractions for use in going to law section
-    let last_char = law_section.chars().last().unwrap();
-    let all_but_last_char = law_section
-        .trim_end_matches(last_char)
-        .trim_end()
-        .to_owned();
-    match last_char {
-        '¼' => all_but_last_char + " 1~4",
-        '½' => all_but_last_char + " 1~2",
-        '¾' => all_but_last_char + " 3~4",
-        '⅐' => all_but_last_char + " 1~7",
-        '⅑' => all_but_last_char + " 1~9",
-        '⅒' => all_but_last_char + " 1~10",
-        '⅓' => all_but_last_char + " 1~3",
-        '⅔' => all_but_last_char + " 2~3",
-        '⅕' => all_but_last_char + " 1~5",
-        '⅖' => all_but_last_char + " 2~5",
-        '⅗' => all_but_last_char + " 3~5",
-        '⅘' => all_but_last_char + " 4~5",
-        '⅙' => all_but_last_char + " 1~6",
-        '⅚' => all_but_last_char + " 5~6",
-        '⅛' => all_but_last_char + " 1~8",
-        '⅜' => all_but_last_char + " 3~8",
-        '⅝' => all_but_last_char + " 5~8",
-        '⅞' => all_but_last_char + " 7~8",
-        _ => law_section.to_string(),
+fn get_fraction_parts(c: char) -> Option<(u8, u8)> {
+    match c {
+        '¼' => Some((1, 4)),
+        '½' => Some((1, 2)),
+        '¾' => Some((3, 4)),
+        '⅐' => Some((1, 7)),
+        '⅑' => Some((1, 9)),
+        '⅒' => Some((1, 10)),
+        '⅓' => Some((1, 3)),
+        '⅔' => Some((2, 3)),
+        '⅕' => Some((1, 5)),
+        '⅖' => Some((2, 5)),
+        '⅗' => Some((3, 5)),
+        '⅘' => Some((4, 5)),
+        '⅙' => Some((1, 6)),
+        '⅚' => Some((5, 6)),
+        '⅛' => Some((1, 8)),
+        '⅜' => Some((3, 8)),
+        '⅝' => Some((5, 8)),
+        '⅞' => Some((7, 8)),
+        _ => None,
     }
 }
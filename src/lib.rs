@@ -1,30 +1,94 @@
-mod bill_section;
-mod law_section;
-mod ma_legislature;
-mod markup;
-
-use crate::{
-    bill_section::BillSection,
-    markup::{MarkedLawSection, MarkupRegex},
+//! Download Massachusetts bills, and mark up the General Laws they amend.
+//!
+//! The types in [`prelude`] are the stable public API: bills and their sections, the law
+//! sections they reference, search results, and the configuration and output options used to
+//! fetch and mark them up. Each is also available at the crate root, and at its canonical path
+//! in its module, such as [`bill_section::BillSection`]. The free functions are re-exported at
+//! the crate root for convenience, but may change as the site and markup evolve.
+
+pub mod bill_section;
+pub mod cache;
+pub mod config;
+pub mod doctor;
+pub mod error;
+pub mod http;
+pub mod law_section;
+pub mod ma_legislature;
+pub mod markup;
+
+pub mod prelude {
+    pub use crate::bill_section::{Bill, BillSection, LineRefs, SectionCounts};
+    pub use crate::cache::Cache;
+    pub use crate::config::Config;
+    pub use crate::error::SpringbokError;
+    pub use crate::law_section::{LawSectionFailure, LawSectionWithText, LawSections, LawSource};
+    pub use crate::ma_legislature::{DocType, SearchEntry};
+    pub use crate::markup::{MarkedLawSection, MarkupFormat, MarkupStyle, Warning};
+}
+
+pub use crate::bill_section::{
+    classify_bill_section, collect_bill, collect_bill_preamble, collect_bill_sections,
+    count_bill_section_types, extract_line_refs, extract_pdf_text, format_bill_sections,
+    get_bill_text_href, get_bill_text_nodes, get_bill_text_nodes_from_html,
+    get_bill_text_nodes_from_pdf_text, init_bill_section_regex, is_pdf_url, parse_bill_metadata,
+    print_bill_section_types, report_unresolved_bill_sections, Bill, BillSection, BillSectionType,
+    LineRefs, SectionCounts,
+};
+// The name used before bill and law section regexes were distinguished
+pub use crate::bill_section::init_bill_section_regex as init_section_regex;
+pub use crate::cache::{read_cache, write_cache, Cache};
+pub use crate::config::{check_deadline, get_site_url, is_past_deadline, Config};
+pub use crate::doctor::{format_selector_checks, run_doctor, SelectorCheck};
+pub use crate::error::SpringbokError;
+#[cfg(feature = "async")]
+pub use crate::http::{build_async_client, fetch_async};
+pub use crate::http::{
+    build_client, fetch, fetch_bytes, get_retry_delay, is_retryable_status, HttpClient,
+    RateLimiter, DEFAULT_RATE_LIMIT, DEFAULT_USER_AGENT,
+};
+#[cfg(feature = "async")]
+pub use crate::law_section::fetch_law_section_async;
+pub use crate::law_section::{
+    collect_law_sections, compare_law_numbers, compare_law_sections, expand_section_range,
+    fetch_law_section, format_law_section, get_law_url, get_section_key, index_law_lines,
+    init_law_section_regex, log_context, normalize_law_number, parse_law_text, run_bounded,
+    run_bounded_until, LawSectionFailure, LawSectionWithText, LawSections, LawSource,
+    LawTextSelectors,
+};
+#[cfg(feature = "serde")]
+pub use crate::ma_legislature::get_search_results_json;
+pub use crate::ma_legislature::{
+    create_general_court_refiner_map, create_refiner_listing, create_refiner_map,
+    filter_search_results_by_date, format_refiner_cache, format_refiner_table, get_bill_url,
+    get_doc_type, get_general_court, get_page_url, get_search_results, parse_filing_date,
+    parse_page_count, parse_refiner_map, parse_search_results, parse_table_date,
+    print_entries_or_append_query_pairs, print_search_results, DocType, SearchEntry,
+};
+pub use crate::markup::{
+    apply_amendments, diff_section_text, escape_asciidoc, escape_html, find_nested_subsection,
+    format_warning_summary, get_paths_with_extension, init_markup_regex, init_punctuation_regex,
+    mark_section_text, mark_text, normalize_punctuation, normalize_quotes, parse_subsection_chain,
+    AsciidocRenderer, HtmlRenderer, MarkedLawSection, MarkupFormat, MarkupRenderer, MarkupStyle,
+    PlainRenderer, PunctuationRegex, SubsectionChain, Warning, WarningKind,
 };
 use clap::Parser;
-use fancy_regex::Regex;
 use indexmap::IndexMap;
-use log::{debug, error, info};
-use scraper::{Element, ElementRef, Html, Selector};
+use log::{debug, error, info, warn, LevelFilter};
 use std::{
+    cmp::Ordering,
     collections::{hash_map::Entry, HashMap},
     error::Error,
     fs,
     fs::File,
-    hash::Hash,
-    io::Write,
+    io,
+    io::{BufRead, Write},
+    iter,
     path::{Path, PathBuf},
     process::Command,
-    sync::{mpsc, mpsc::Sender},
-    thread,
+    sync::{mpsc, Arc},
+    time::{Duration, Instant},
 };
-use url::{quirks::search, Url};
+use url::Url;
 
 // See:
 // - https://docs.rs/clap/latest/clap/_derive/_tutorial/chapter_0/index.html#
@@ -35,125 +99,669 @@ use url::{quirks::search, Url};
 // - https://docs.rs/url/latest/url/#
 // - https://docs.rs/url/latest/url/struct.Url.html#
 
+/// Folder, within the output folder, into which modified law sections are written
+pub const LAW_FOLDER: &str = "modified-laws";
+
+/// File, within the output folder, which lists each generated file
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Name each law section file by its law chapter key, such as "40-7"
+pub const DEFAULT_FILE_NAME_TEMPLATE: &str = "{key}";
+
+// Placeholders allowed in a file name template, those naming the law section last
+const BILL_PLACEHOLDERS: [&str; 2] = ["{court}", "{bill}"];
+const LAW_SECTION_PLACEHOLDERS: [&str; 3] = ["{chapter}", "{section}", "{key}"];
+
 /// Produce strikethrough and underline markup for a bill before the Massachusetts legislature
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     /// List legislation for the current general court
     #[arg(short = 'l', long)]
     pub list: bool,
 
-    /// Search for legislation using this search term
+    /// Search for legislation using this search term, which --download treats as a bill number
     #[arg(short = 's', long)]
     pub search_term: Option<String>,
 
-    /// Identify legislation from this legislative session
-    #[arg(short = 'C', long, default_value = "193rd", num_args = 0..=1, default_missing_value = "MISSING")]
-    pub general_court: Option<String>,
+    /// Identify legislation from this legislative session, or search each of these comma-separated sessions
+    #[arg(short = 'C', long, default_value = "193rd", num_args = 0..=1, default_missing_value = "MISSING", value_delimiter = ',')]
+    pub general_court: Vec<String>,
+
+    /// Include legislation in this branch of the legislature, or these comma-separated branches
+    #[arg(short = 'B', long, num_args = 0..=1, default_missing_value = "MISSING", value_delimiter = ',')]
+    pub branch: Vec<String>,
+
+    /// Include legislation sponsored by this legislator, or these comma-separated legislators
+    #[arg(short = 'L', long, num_args = 0..=1, default_missing_value = "MISSING", value_delimiter = ',')]
+    pub sponsor_legislator: Vec<String>,
+
+    /// Include legislation sponsored by this legislative committee, or these comma-separated committees
+    #[arg(short = 'M', long, num_args = 0..=1, default_missing_value = "MISSING", value_delimiter = ',')]
+    pub sponsor_committee: Vec<String>,
 
-    /// Include legislation in this branch of the legislature
-    #[arg(short = 'B', long, num_args = 0..=1, default_missing_value = "MISSING")]
-    pub branch: Option<String>,
+    /// Include legislation sponsored by a governor, or state organization, or these comma-separated sponsors
+    #[arg(short = 'O', long, num_args = 0..=1, default_missing_value = "MISSING", value_delimiter = ',')]
+    pub sponsor_other: Vec<String>,
 
-    /// Include legislation sponsored by this legislator
-    #[arg(short = 'L', long, num_args = 0..=1, default_missing_value = "MISSING")]
-    pub sponsor_legislator: Option<String>,
+    /// Identify legislation of this document type, or these comma-separated document types
+    #[arg(short = 'D', long, num_args = 0..=1, default_missing_value = "MISSING", value_delimiter = ',')]
+    pub document_type: Vec<String>,
 
-    /// Include legislation sponsored by this legislative committee
-    #[arg(short = 'M', long, num_args = 0..=1, default_missing_value = "MISSING")]
-    pub sponsor_committee: Option<String>,
+    /// List the committees which sponsor legislation in the general court, then stop
+    #[arg(long)]
+    pub list_committees: bool,
 
-    /// Include legislation sponsored by a governor, or state organization
-    #[arg(short = 'O', long, num_args = 0..=1, default_missing_value = "MISSING")]
-    pub sponsor_other: Option<String>,
+    /// List the legislators who sponsor legislation in the general court, then stop
+    #[arg(long)]
+    pub list_legislators: bool,
 
-    /// Identify legislation of this document type
-    #[arg(short = 'D', long, num_args = 0..=1, default_missing_value = "MISSING")]
-    pub document_type: Option<String>,
+    /// List the document types of legislation in the general court, then stop
+    #[arg(long)]
+    pub list_document_types: bool,
+
+    /// Include legislation filed on or after this date, formatted as YYYY-MM-DD, by the filing
+    /// date listed in the search results
+    #[arg(long, visible_alias = "since", value_parser = parse_filing_date)]
+    pub filed_after: Option<String>,
+
+    /// Include legislation filed on or before this date, formatted as YYYY-MM-DD, by the filing
+    /// date listed in the search results
+    #[arg(long, visible_alias = "until", value_parser = parse_filing_date)]
+    pub filed_before: Option<String>,
 
     /// Download the text of a bill when searching with the bill number
     #[arg(short = 'd', long)]
     pub download: bool,
 
+    /// List only refiner values containing this text, when listing refiner values
+    #[arg(long)]
+    pub filter: Option<String>,
+
+    /// Download this bill, such as H.4607, directly, whether or not a search would match it,
+    /// or these comma-separated bills, concurrently, each into its own folder
+    #[arg(short = 'b', long, value_delimiter = ',', conflicts_with_all = ["search_term", "bill_file", "stdin"])]
+    pub bill: Vec<String>,
+
+    /// Download each bill in this file, one bill number per line, as with --bill
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["search_term", "bill_file", "stdin"])]
+    pub bill_list: Option<PathBuf>,
+
     /// Download text into this filename
     #[arg(short = 'o', long)]
     pub output_filename: Option<String>,
+
+    /// Write output into this folder, instead of one named after the bill number
+    #[arg(long)]
+    pub output_dir: Option<String>,
+
+    /// Print only requested output, without warnings or progress
+    #[arg(short = 'q', long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Log more detail, repeating for even more detail
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Download at most this many law sections concurrently
+    #[arg(short = 'j', long, default_value_t = 4)]
+    pub jobs: usize,
+
+    /// List the law sections the bill references, without downloading them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print only the counts of each type of bill section, downloading the bill when searching
+    /// with its number, but no law sections
+    #[arg(long, conflicts_with_all = ["dry_run", "dump_sections", "output_filename"])]
+    pub counts_only: bool,
+
+    /// Download law sections one at a time, in order, so output is the same from run to run
+    #[arg(long)]
+    pub sequential: bool,
+
+    /// Write one asciidoc file with all modified law sections in bill order, instead of one per law section
+    #[arg(long)]
+    pub single_doc: bool,
+
+    /// Name law section files with this template of {court}, {bill}, {chapter}, {section}, and
+    /// {key}, such as "{court}-{bill}-ch{chapter}-s{section}"
+    #[arg(long, default_value = DEFAULT_FILE_NAME_TEMPLATE, value_parser = parse_file_name_template)]
+    pub file_name_template: String,
+
+    /// Format of the marked up law sections
+    #[arg(long, value_enum, default_value_t = MarkupFormat::Adoc)]
+    pub format: MarkupFormat,
+
+    /// Write asciidoc files, but do not render them with asciidoctor
+    #[arg(long)]
+    pub no_asciidoctor: bool,
+
+    /// Render asciidoc files with this asciidoctor executable
+    #[arg(long, default_value = "asciidoctor")]
+    pub asciidoctor_bin: String,
+
+    /// Render asciidoc files with asciidoctor, even if their HTML is newer
+    #[arg(long, conflicts_with = "no_asciidoctor")]
+    pub force: bool,
+
+    /// Keep asciidoc files after rendering them with asciidoctor, the default
+    #[arg(long, overrides_with = "no_keep_adoc")]
+    pub keep_adoc: bool,
+
+    /// Remove the asciidoc files written, once rendered with asciidoctor
+    #[arg(long, overrides_with = "keep_adoc", conflicts_with = "no_asciidoctor")]
+    pub no_keep_adoc: bool,
+
+    /// Write asciidoc files to a temporary folder, removed on exit, and only their rendered HTML
+    /// to the output folder
+    #[arg(long, conflicts_with_all = ["no_asciidoctor", "keep_adoc"])]
+    pub adoc_temp_dir: bool,
+
+    /// Mark struck text with this asciidoc role
+    #[arg(long, default_value = "red")]
+    pub strike_role: String,
+
+    /// Mark inserted text with this asciidoc role
+    #[arg(long, default_value = "blue")]
+    pub insert_role: String,
+
+    /// Scrape the legislature website at this base URL
+    #[arg(
+        long,
+        env = "SPRINGBOK_BASE_URL",
+        default_value = "https://malegislature.gov"
+    )]
+    pub base_url: Url,
+
+    /// Retry failed requests this many times
+    #[arg(long, default_value_t = 3)]
+    pub retries: u32,
+
+    /// Wait this many milliseconds before the first retry, doubling for each retry after
+    #[arg(long, default_value_t = 500)]
+    pub retry_base_delay: u64,
+
+    /// Give up on a request after this many seconds
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+
+    /// Stop after this many seconds for the whole run, writing what completed so far
+    #[arg(long, value_name = "SECS")]
+    pub deadline: Option<u64>,
+
+    /// Make at most this many requests per second, across all jobs, or any number if 0
+    #[arg(long, value_name = "REQ_PER_SEC", default_value_t = DEFAULT_RATE_LIMIT)]
+    pub rate_limit: f64,
+
+    /// Find law text by the parent of the heading this CSS selector selects, instead of the
+    /// default selectors, trying each in turn when repeated
+    #[arg(long, value_name = "SELECTOR")]
+    pub law_heading_selector: Vec<String>,
+
+    /// Find law text in the element this CSS selector selects, if no heading selector matches,
+    /// trying each in turn when repeated
+    #[arg(long, value_name = "SELECTOR")]
+    pub law_container_selector: Vec<String>,
+
+    /// Identify requests with this User-Agent
+    #[arg(long, default_value = DEFAULT_USER_AGENT)]
+    pub user_agent: String,
+
+    /// Cache downloaded law sections and search refiners in this folder
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Use cached downloads made at most this many seconds ago
+    #[arg(long, default_value_t = 86400)]
+    pub cache_ttl: u64,
+
+    /// Download law sections and search refiners again, even if cached
+    #[arg(long)]
+    pub refresh_cache: bool,
+
+    /// Print section counts and bill sections as JSON instead of text
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    pub json: bool,
+
+    /// List legislation, or search results, as a JSON array instead of a table
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    pub list_json: bool,
+
+    /// Print each bill section's number, type, and law sections, without marking up the bill
+    #[arg(long)]
+    pub dump_sections: bool,
+
+    /// Exit with an error if any law section fails to download or mark up, once all have been
+    /// tried, instead of writing what succeeded
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Read the text of a bill from this saved HTML file instead of downloading it
+    #[arg(long)]
+    pub bill_file: Option<PathBuf>,
+
+    /// Read the text of a bill from stdin, one text node per line, instead of downloading it
+    #[arg(long, conflicts_with = "bill_file")]
+    pub stdin: bool,
+
+    /// Write section counts for each bill in the search results to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+
+    /// Check that the selectors used to scrape each page still match the legislature website,
+    /// then stop
+    #[arg(long)]
+    pub doctor: bool,
+
+    /// Follow search results across at most this many pages
+    #[arg(long, default_value_t = 10)]
+    pub max_pages: usize,
+}
+
+pub fn get_log_level(cli: &Cli) -> LevelFilter {
+    // Warn by default, and let --quiet and --verbose lower or raise the level
+    match (cli.quiet, cli.verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+pub fn init_config(cli: &Cli) -> Result<Config, Box<dyn Error>> {
+    Ok(Config {
+        base_url: cli.base_url.clone(),
+        retries: cli.retries,
+        retry_base_delay: Duration::from_millis(cli.retry_base_delay),
+        client: build_client(Duration::from_secs(cli.timeout), &cli.user_agent)?,
+        rate_limiter: Arc::new(RateLimiter::new(cli.rate_limit)?),
+        law_text_selectors: init_law_text_selectors(cli),
+        deadline: cli
+            .deadline
+            .map(|deadline| Instant::now() + Duration::from_secs(deadline)),
+    })
+}
+
+pub fn init_law_text_selectors(cli: &Cli) -> LawTextSelectors {
+    // Selectors given replace the defaults, so a changed page layout can be followed
+    let mut law_text_selectors = LawTextSelectors::default();
+    if !cli.law_heading_selector.is_empty() || !cli.law_container_selector.is_empty() {
+        law_text_selectors.heading = cli.law_heading_selector.clone();
+        law_text_selectors.container = cli.law_container_selector.clone();
+    }
+    law_text_selectors
+}
+
+pub fn init_markup_style(cli: &Cli) -> MarkupStyle {
+    MarkupStyle {
+        strike_role: cli.strike_role.clone(),
+        insert_role: cli.insert_role.clone(),
+    }
+}
+
+pub fn init_file_naming(cli: &Cli, bill: &Bill) -> FileNaming {
+    FileNaming {
+        template: cli.file_name_template.clone(),
+        court: bill.general_court.clone(),
+        bill: bill.bill_number.clone(),
+        ..FileNaming::default()
+    }
+}
+
+pub fn init_cache(cli: &Cli) -> Option<Cache> {
+    // Cache only when a cache folder is given
+    cli.cache_dir.as_ref().map(|cache_dir| Cache {
+        cache_dir: cache_dir.clone(),
+        ttl: Duration::from_secs(cli.cache_ttl),
+        refresh: cli.refresh_cache,
+    })
 }
 
 pub fn create_search_results_map(
     cli: &Cli,
-) -> (IndexMap<String, ma_legislature::SearchEntry>, String) {
+    config: &Config,
+) -> Result<(IndexMap<String, SearchEntry>, String), SpringbokError> {
     // Parse command line arguments and construct search URL
     info!("Constructing search URL");
-    let (do_search, search_url, search_term) = ma_legislature::get_search_page(&cli);
+    let (do_search, search_urls, search_term) = ma_legislature::get_search_page(cli, config)?;
 
     // Get and print the search results
+    #[cfg(feature = "serde")]
+    let list_json = cli.list_json;
+    #[cfg(not(feature = "serde"))]
+    let list_json = false;
     let mut search_results_map = IndexMap::new();
-    if do_search || cli.list {
-        info!("Value for search URL: {search_url}");
-        search_results_map = ma_legislature::get_and_print_search_results(&search_url);
+    if do_search || cli.list || list_json {
+        for (general_court, search_url) in &search_urls {
+            info!("Value for search URL: {search_url}");
+            let court_results_map =
+                ma_legislature::get_search_results(config, search_url, cli.max_pages)?;
+
+            // Key results by court and bill number when searching several courts, since each
+            // court numbers its bills anew
+            if search_urls.len() > 1 {
+                search_results_map.extend(court_results_map.into_iter().map(
+                    |(bill_number, search_entry)| {
+                        (format!("{general_court}:{bill_number}"), search_entry)
+                    },
+                ));
+            } else {
+                search_results_map.extend(court_results_map);
+            }
+        }
+        search_results_map = ma_legislature::filter_search_results_by_date(
+            search_results_map,
+            cli.filed_after.as_deref(),
+            cli.filed_before.as_deref(),
+        );
+        #[cfg(feature = "serde")]
+        if list_json {
+            let search_results_json = ma_legislature::get_search_results_json(&search_results_map);
+            let search_results_json = serde_json::to_string_pretty(&search_results_json)
+                .map_err(|error| SpringbokError::parse(error.to_string()))?;
+            println!("{search_results_json}");
+        } else {
+            ma_legislature::print_search_results(&search_results_map);
+        }
+        #[cfg(not(feature = "serde"))]
+        ma_legislature::print_search_results(&search_results_map);
     }
     // Return search results and term
-    (search_results_map, search_term)
+    Ok((search_results_map, search_term))
+}
+
+pub fn create_bill(
+    config: &Config,
+    bill_number: &str,
+    search_entry: &SearchEntry,
+) -> Result<(Bill, SectionCounts), SpringbokError> {
+    // Amendment pages lay out their text differently from bill pages, so skip them clearly
+    if search_entry.doc_type == DocType::Amendment {
+        return Err(SpringbokError::invalid_argument(format!(
+            "{bill_number} is an amendment, not a bill, so cannot be downloaded: {}",
+            search_entry.bill_url
+        )));
+    }
+    info!("Value for bill URL: {}", search_entry.bill_url);
+    let bill = bill_section::collect_bill(config, bill_number, search_entry)?;
+    bill_section::report_unresolved_bill_sections(&bill.bill_sections);
+
+    // Count type of bill sections with regex
+    let section_regex = bill_section::init_bill_section_regex();
+    let section_counts =
+        bill_section::count_bill_section_types(&bill.bill_sections, &section_regex);
+    Ok((bill, section_counts))
+}
+
+pub fn create_bill_from_bill_number(
+    config: &Config,
+    general_court: &str,
+    bill_number: &str,
+) -> Result<(Bill, SectionCounts), SpringbokError> {
+    // Download the bill directly, since the bill page gives any metadata a search would
+    let search_entry = SearchEntry {
+        bill_url: get_bill_url(config, general_court, bill_number)?,
+        bill_sponsor: String::new(),
+        bill_summary: String::new(),
+        doc_type: DocType::Bill,
+        filed_date: None,
+    };
+    create_bill(config, bill_number, &search_entry)
+}
+
+pub fn get_bill_numbers(cli: &Cli) -> Result<Vec<String>, SpringbokError> {
+    // Combine the bills given with --bill and --bill-list, in order, once each
+    let mut bill_numbers: Vec<String> = cli.bill.clone();
+    if let Some(bill_list) = &cli.bill_list {
+        let file = File::open(bill_list).map_err(|error| SpringbokError::io(bill_list, error))?;
+        for line in io::BufReader::new(file).lines() {
+            bill_numbers.push(line.map_err(|error| SpringbokError::io(bill_list, error))?);
+        }
+    }
+    let mut unique_bill_numbers: Vec<String> = Vec::new();
+    for bill_number in bill_numbers {
+        let bill_number = bill_number.trim();
+        if !bill_number.is_empty() && !unique_bill_numbers.iter().any(|b| b == bill_number) {
+            unique_bill_numbers.push(bill_number.to_string());
+        }
+    }
+    Ok(unique_bill_numbers)
 }
 
-pub fn create_bill(search_entry: &ma_legislature::SearchEntry) -> Vec<BillSection> {
-    let bill_url = &search_entry.bill_url;
-    info!("Value for bill URL: {bill_url}");
-    let text_nodes = bill_section::get_bill_text_nodes(bill_url);
+pub fn create_bill_from_html(html: &str) -> Result<(Bill, SectionCounts), Box<dyn Error>> {
+    let text_nodes = bill_section::get_bill_text_nodes_from_html(html)?;
+    Ok(create_bill_from_text_nodes(&text_nodes))
+}
 
-    // Collect bill sections and law sections into structs with regex
+pub fn read_text_nodes(reader: impl BufRead) -> io::Result<Vec<String>> {
+    // Read one text node per line, as saved from a bill text page
+    reader.lines().collect()
+}
+
+pub fn create_bill_from_text_nodes(text_nodes: &[String]) -> (Bill, SectionCounts) {
+    // Collect bill sections and law sections into structs with regex, leaving the bill metadata
+    // to the caller
     let section_regex = bill_section::init_bill_section_regex();
-    let bill = bill_section::collect_bill_sections(&text_nodes, &section_regex);
+    let bill = Bill {
+        preamble: bill_section::collect_bill_preamble(text_nodes, &section_regex),
+        bill_sections: bill_section::collect_bill_sections(text_nodes, &section_regex),
+        ..Bill::default()
+    };
+    bill_section::report_unresolved_bill_sections(&bill.bill_sections);
 
-    // Count and print type of bill sections with regex
-    let section_counts = bill_section::count_bill_section_types(&bill, &section_regex);
-    bill_section::print_bill_section_types(section_counts);
-    bill
+    // Count type of bill sections with regex
+    let section_counts =
+        bill_section::count_bill_section_types(&bill.bill_sections, &section_regex);
+    (bill, section_counts)
+}
+
+#[cfg(feature = "serde")]
+pub fn print_bill_json(bill: &Bill, section_counts: &SectionCounts) -> Result<(), Box<dyn Error>> {
+    // Print bill metadata, section counts, and bill sections as a single JSON document
+    let bill_json = serde_json::json!({
+        "bill_number": bill.bill_number,
+        "title": bill.title,
+        "sponsor": bill.sponsor,
+        "general_court": bill.general_court,
+        "preamble": bill.preamble,
+        "section_counts": section_counts,
+        "bill_sections": bill.bill_sections,
+    });
+    println!("{}", serde_json::to_string_pretty(&bill_json)?);
+    Ok(())
 }
 
-pub fn create_law_sections_text(bill: &Vec<BillSection>) -> Vec<law_section::LawSectionWithText> {
+// Sorted (chapter, section) pairs, and the bill section numbers referencing each law section key
+pub type RequiredLawSections = (Vec<(String, String)>, HashMap<String, Vec<String>>);
+
+pub fn get_required_law_sections(bill: &[BillSection]) -> RequiredLawSections {
     // Iterate through bill to get list of all needed sections for downloading
     let mut required_law_sections: Vec<(String, String)> = Vec::new();
     let mut law_section_bill_sections: HashMap<String, Vec<String>> = HashMap::new();
     for bill_section in bill {
         for law_section in &bill_section.law_sections.section_numbers {
-            let law_chapter = bill_section.law_sections.chapter_number.clone();
-            let section_key = law_section::get_section_key(&law_chapter, law_section);
+            // Normalize numbers, so each law section is downloaded once however it is spelled
+            let law_chapter =
+                law_section::normalize_law_number(&bill_section.law_sections.chapter_number);
+            let law_section = law_section::normalize_law_number(law_section);
+            let section_key = law_section::get_section_key(&law_chapter, &law_section);
 
             match law_section_bill_sections.entry(section_key) {
                 Entry::Vacant(entry) => {
                     entry.insert(vec![String::from(&bill_section.section_number)]);
                 }
                 Entry::Occupied(mut entry) => {
-                    entry
-                        .get_mut()
-                        .push(String::from(&bill_section.section_number));
+                    if !entry.get().contains(&bill_section.section_number) {
+                        entry
+                            .get_mut()
+                            .push(String::from(&bill_section.section_number));
+                    }
                 }
             }
 
             // Push required law section to list
             // TODO: Review
-            required_law_sections.push((law_chapter, law_section.to_string()))
+            required_law_sections.push((law_chapter, law_section))
         }
     }
-    // Remove duplicates
-    required_law_sections.sort();
+    // Remove duplicates, and order by chapter and section number
+    required_law_sections.sort_by(law_section::compare_law_sections);
     required_law_sections.dedup();
+    (required_law_sections, law_section_bill_sections)
+}
+
+pub fn print_required_law_sections(bill: &[BillSection]) {
+    // List each law section which would be downloaded, and the bill sections referencing it
+    let (required_law_sections, law_section_bill_sections) = get_required_law_sections(bill);
+    println!("Law sections to download: {}", required_law_sections.len());
+    for (law_chapter, law_section) in &required_law_sections {
+        let section_key = law_section::get_section_key(law_chapter, law_section);
+        let bill_section_numbers: Vec<String> = law_section_bill_sections.get(&section_key).map_or(
+            Vec::new(),
+            |bill_section_numbers| {
+                bill_section_numbers
+                    .iter()
+                    .map(|bill_section_number| format!("SECTION {bill_section_number}"))
+                    .collect()
+            },
+        );
+        println!(
+            "Chapter {law_chapter}, Section {law_section} — {}",
+            bill_section_numbers.join(", ")
+        );
+    }
+}
+
+pub fn create_law_sections_text(
+    config: &Config,
+    bill: &[BillSection],
+    jobs: usize,
+    sequential: bool,
+    cache: Option<Cache>,
+    progress: bool,
+) -> (
+    Vec<law_section::LawSectionWithText>,
+    Vec<law_section::LawSectionFailure>,
+) {
+    let (required_law_sections, law_section_bill_sections) = get_required_law_sections(bill);
 
-    // Download required law sections concurrently
-    let (tx, rx) = mpsc::channel();
-    let (law_chapter, law_section) = required_law_sections.pop().unwrap();
-    for (law_chapter, law_section) in required_law_sections {
-        law_section::download_law_section(&law_chapter, &law_section, tx.clone());
+    // Nothing to download when the bill references no law sections
+    if required_law_sections.is_empty() {
+        info!("Bill references no law sections");
+        return (Vec::new(), Vec::new());
     }
-    // Download final law section
-    law_section::download_law_section(&law_chapter, &law_section, tx);
 
-    // Collect law sections and create struct
+    // Download required law sections one at a time in sorted order, for stable output, or
+    // concurrently, with at most jobs downloads in flight
+    let required_count = required_law_sections.len();
+    let required_keys = required_law_sections.clone();
+    let mut handles = Vec::new();
+    let law_section_results: Box<dyn Iterator<Item = (String, String, Result<String, String>)>> =
+        if sequential {
+            Box::new(
+                required_law_sections
+                    .into_iter()
+                    .map(move |(law_chapter, law_section)| {
+                        let text = law_section::fetch_law_section(
+                            config,
+                            &law_chapter,
+                            &law_section,
+                            cache.as_ref(),
+                        )
+                        .map_err(|error| error.to_string());
+                        (law_chapter, law_section, text)
+                    }),
+            )
+        } else {
+            let (tx, rx) = mpsc::channel();
+            handles =
+                law_section::download_law_sections(config, required_law_sections, jobs, cache, tx);
+            // Stop waiting for downloads still in flight at the deadline, if any
+            Box::new(iter::from_fn(move || match config.deadline {
+                Some(deadline) => rx
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => rx.recv().ok(),
+            }))
+        };
+
+    let (law_sections_text, mut law_section_failures) = collect_law_sections_text(
+        law_section_results,
+        required_count,
+        &law_section_bill_sections,
+        progress,
+    );
+
+    // Report the law sections not downloaded by the deadline as failures, and leave downloads
+    // in flight behind, so what completed is returned without waiting, since workers start no
+    // more downloads, and drop results sent once the receiver is gone
+    if is_past_deadline(config) {
+        for (law_chapter, law_section) in required_keys {
+            let law_chapter_key = law_section::get_section_key(&law_chapter, &law_section);
+            let is_received = law_sections_text
+                .iter()
+                .any(|law_section_text| law_section_text.law_chapter_key == law_chapter_key)
+                || law_section_failures.iter().any(|law_section_failure| {
+                    law_section_failure.law_chapter == law_chapter
+                        && law_section_failure.law_section == law_section
+                });
+            if !is_received {
+                law_section_failures.push(law_section::LawSectionFailure {
+                    law_chapter,
+                    law_section,
+                    message: String::from("Deadline exceeded before download finished"),
+                });
+            }
+        }
+        return (law_sections_text, law_section_failures);
+    }
+
+    // Wait for all downloads to finish, so none are silently lost
+    for handle in handles {
+        if handle.join().is_err() {
+            error!("Law section download thread panicked");
+        }
+    }
+    (law_sections_text, law_section_failures)
+}
+
+fn collect_law_sections_text(
+    law_section_results: impl Iterator<Item = (String, String, Result<String, String>)>,
+    required_count: usize,
+    law_section_bill_sections: &HashMap<String, Vec<String>>,
+    progress: bool,
+) -> (
+    Vec<law_section::LawSectionWithText>,
+    Vec<law_section::LawSectionFailure>,
+) {
+    // Collect law sections and create struct, collecting failures separately
+    let law_section_regex = law_section::init_law_section_regex();
     let mut law_sections_text: Vec<law_section::LawSectionWithText> = vec![];
-    for (law_chapter, law_section, text) in rx {
-        println!(
+    let mut law_section_failures: Vec<law_section::LawSectionFailure> = vec![];
+    for (received_count, (law_chapter, law_section, text)) in law_section_results.enumerate() {
+        // Report progress on stderr, so stdout stays clean
+        if progress {
+            eprint!(
+                "\rDownloaded {} of {required_count} law sections",
+                received_count + 1
+            );
+        }
+        let text = match text {
+            Ok(text) => text,
+            Err(message) => {
+                law_section_failures.push(law_section::LawSectionFailure {
+                    law_chapter,
+                    law_section,
+                    message,
+                });
+                continue;
+            }
+        };
+        info!(
             "Got law section: {:?} of chapter {:?}",
             law_section, law_chapter
         );
@@ -163,6 +771,7 @@ pub fn create_law_sections_text(bill: &Vec<BillSection>) -> Vec<law_section::Law
             Some(b) => {
                 let law_section_text = law_section::LawSectionWithText {
                     law_chapter_key,
+                    line_index: Some(law_section::index_law_lines(&text, &law_section_regex)),
                     text,
                     bill_section_keys: b.to_vec(),
                 };
@@ -172,6 +781,7 @@ pub fn create_law_sections_text(bill: &Vec<BillSection>) -> Vec<law_section::Law
                 // TODO: This should not happen?
                 let law_section_text = law_section::LawSectionWithText {
                     law_chapter_key,
+                    line_index: Some(law_section::index_law_lines(&text, &law_section_regex)),
                     text,
                     bill_section_keys: Vec::new(),
                 };
@@ -180,74 +790,627 @@ pub fn create_law_sections_text(bill: &Vec<BillSection>) -> Vec<law_section::Law
         }
     }
 
-    law_sections_text
+    if progress {
+        eprintln!();
+    }
+    (law_sections_text, law_section_failures)
 }
-pub fn write_bill(bill: &Vec<BillSection>, output_filename: &String, output_folder: &String) {
+
+#[cfg(feature = "async")]
+pub async fn create_law_sections_text_async(
+    config: &Config,
+    client: &reqwest::Client,
+    bill: &[BillSection],
+    jobs: usize,
+    cache: Option<Cache>,
+    progress: bool,
+) -> (
+    Vec<law_section::LawSectionWithText>,
+    Vec<law_section::LawSectionFailure>,
+) {
+    let (required_law_sections, law_section_bill_sections) = get_required_law_sections(bill);
+
+    // Nothing to download when the bill references no law sections
+    if required_law_sections.is_empty() {
+        info!("Bill references no law sections");
+        return (Vec::new(), Vec::new());
+    }
+
+    // Download required law sections concurrently on the current task, with at most jobs
+    // downloads in flight, keeping results in sorted order
+    let required_count = required_law_sections.len();
+    let semaphore = tokio::sync::Semaphore::new(jobs.max(1));
+    let law_section_results = futures::future::join_all(required_law_sections.into_iter().map(
+        |(law_chapter, law_section)| {
+            let semaphore = &semaphore;
+            let cache = cache.as_ref();
+            async move {
+                let _permit = semaphore.acquire().await.unwrap();
+                let text = law_section::fetch_law_section_async(
+                    config,
+                    client,
+                    &law_chapter,
+                    &law_section,
+                    cache,
+                )
+                .await
+                .map_err(|error| error.to_string());
+                (law_chapter, law_section, text)
+            }
+        },
+    ))
+    .await;
+
+    collect_law_sections_text(
+        law_section_results.into_iter(),
+        required_count,
+        &law_section_bill_sections,
+        progress,
+    )
+}
+
+pub fn get_output_folder(cli: &Cli, bill_number: &str) -> String {
+    // Use the given folder, or a folder named after the bill number, made safe for a path
+    match &cli.output_dir {
+        Some(output_dir) => output_dir.clone(),
+        None => sanitize_folder_name(bill_number),
+    }
+}
+
+pub fn sanitize_folder_name(name: &str) -> String {
+    // Replace path separators and characters not allowed in file names on common platforms
+    let folder_name: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let folder_name = folder_name.trim_end_matches(['.', ' ']);
+    if folder_name.is_empty() {
+        String::from("bill")
+    } else {
+        folder_name.to_string()
+    }
+}
+
+pub fn write_bill(
+    bill: &[BillSection],
+    output_filename: &str,
+    output_folder: &str,
+) -> Result<(), Box<dyn Error>> {
     // Print each text node of the bill to a file
-    fs::create_dir_all(output_folder);
-    let str_path = [output_folder, output_filename.as_str()].join("/");
+    fs::create_dir_all(output_folder)?;
+    let str_path = [output_folder, output_filename].join("/");
     let path = Path::new(&str_path);
     let display = path.display();
-    let mut file = match File::create(&path) {
-        Err(error) => panic!("Couldn't create {}: {}", display, error),
-        Ok(file) => file,
-    };
+    let mut file =
+        File::create(path).map_err(|error| format!("Couldn't create {}: {}", display, error))?;
     for bill_section in bill {
-        match file.write(format!("{}\n", bill_section.text).as_bytes()) {
-            Err(error) => panic!("Couldn't write to {}: {}", display, error),
-            Ok(_) => (),
-        }
+        file.write_all(format!("{}\n", bill_section.text).as_bytes())
+            .map_err(|error| format!("Couldn't write to {}: {}", display, error))?;
+    }
+    Ok(())
+}
+
+pub fn write_section_counts_csv(
+    bills_section_counts: &[(String, SectionCounts)],
+    csv_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    // Write a header, then one row of section counts per bill
+    let mut file = File::create(csv_path)
+        .map_err(|error| format!("Couldn't create {}: {}", csv_path.display(), error))?;
+    writeln!(
+        file,
+        "bill_number,total,amending,amending_by_striking_and_inserting,amending_by_striking,amending_by_inserting,repealing,effective_date,appropriation,other"
+    )?;
+    for (bill_number, section_counts) in bills_section_counts {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{},{}",
+            bill_number,
+            section_counts.total,
+            section_counts.amending,
+            section_counts.amending_by_striking_and_inserting,
+            section_counts.amending_by_striking,
+            section_counts.amending_by_inserting,
+            section_counts.repealing,
+            section_counts.effective_date,
+            section_counts.appropriation,
+            section_counts.other
+        )?;
+    }
+    Ok(())
+}
+
+pub fn format_section_counts_summary(bills_section_counts: &[(String, SectionCounts)]) -> String {
+    // Align a row of the main section counts per bill under a header, as for the CSV file
+    let bill_width = bills_section_counts
+        .iter()
+        .map(|(bill_number, _)| bill_number.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("Bill".len());
+    let mut summary = format!(
+        "{:bill_width$}  Total  Amending  Repealing  Effective date  Appropriation  Other\n",
+        "Bill"
+    );
+    for (bill_number, section_counts) in bills_section_counts {
+        summary.push_str(&format!(
+            "{bill_number:bill_width$}  {:>5}  {:>8}  {:>9}  {:>14}  {:>13}  {:>5}\n",
+            section_counts.total,
+            section_counts.amending,
+            section_counts.repealing,
+            section_counts.effective_date,
+            section_counts.appropriation,
+            section_counts.other
+        ));
     }
+    summary
 }
+
 pub fn write_asciidocs(
     law_sections_text: Vec<law_section::LawSectionWithText>,
-    bill_sections_text: &Vec<BillSection>,
-    output_folder: &String,
-    law_folder: &str,
-) -> Result<(), std::io::Error> {
-    let markup_regex = markup::init_markup_regex();
-    let mut all_markup: Vec<MarkedLawSection> = Vec::new();
+    bill_sections_text: &[BillSection],
+    output_folder: &str,
+    file_naming: &FileNaming,
+    markup_style: &MarkupStyle,
+    format: MarkupFormat,
+    single_doc: bool,
+) -> Result<Vec<Warning>, std::io::Error> {
+    // Return the warnings met while marking up, including each law section which failed
+    let mut markup_regex = markup::init_markup_regex();
+    markup_regex.style = markup_style.clone();
+    markup_regex.format = format;
+    let renderer = format.renderer(markup_style);
+    let extension = format.extension();
+    let mut all_markup: Vec<(usize, MarkedLawSection)> = Vec::new();
+    let mut warnings: Vec<Warning> = Vec::new();
+    if single_doc {
+        fs::create_dir_all(output_folder)?;
+    } else {
+        fs::create_dir_all(format!("{output_folder}/{}", file_naming.law_folder))?;
+    }
     for law_section in law_sections_text {
         let file_name = &law_section.law_chapter_key;
-        if let Some(marked_law_section) =
+        if let Some(mut marked_law_section) =
             markup::mark_section_text(&law_section, bill_sections_text, &markup_regex)
         {
-            fs::create_dir_all(format!("{output_folder}/{law_folder}"));
-            let mut file = File::create(format!("{output_folder}/modified-laws/{file_name}.adoc"))?;
-            file.write_all(marked_law_section.text.as_ref())?;
-            all_markup.push(marked_law_section);
+            warnings.append(&mut marked_law_section.warnings);
+            if !single_doc {
+                let document = renderer.document(file_name, &marked_law_section.text);
+                write_if_changed(
+                    format!(
+                        "{output_folder}/{}",
+                        file_naming.law_section_file(file_name, extension)
+                    ),
+                    document.as_ref(),
+                )?;
+            }
+
+            // Position of the first bill section which references the law section
+            let bill_position = law_section
+                .bill_section_keys
+                .iter()
+                .filter_map(|key| {
+                    bill_sections_text
+                        .iter()
+                        .position(|bill_section| &bill_section.section_number == key)
+                })
+                .min()
+                .unwrap_or(usize::MAX);
+            all_markup.push((bill_position, marked_law_section));
         } else {
-            println!("Could not mark up law section: {file_name}")
+            warn!(
+                "{} could not mark up law section",
+                log_context(None, Some(file_name))
+            );
+            warnings.push(Warning {
+                kind: WarningKind::MarkupFailed,
+                bill_section_number: None,
+                law_chapter_key: Some(file_name.clone()),
+            });
         }
     }
 
-    // Format strings add leading zeros to chapter/section numbers to ensure expected sort
-    all_markup.sort_unstable_by_key(|item| {
-        (
-            format!("{:0>5}", item.chapter_number.clone()),
-            format!("{:0>5}", item.section_number.clone()),
-        )
+    // Order by bill position, for a single document, then by chapter and section number
+    all_markup.sort_by(|(a_position, a_item), (b_position, b_item)| {
+        let position_ordering = if single_doc {
+            a_position.cmp(b_position)
+        } else {
+            Ordering::Equal
+        };
+        position_ordering
+            .then_with(|| {
+                law_section::compare_law_numbers(&a_item.chapter_number, &b_item.chapter_number)
+            })
+            .then_with(|| {
+                law_section::compare_law_numbers(&a_item.section_number, &b_item.section_number)
+            })
     });
-    let mut file = File::create(format!("{output_folder}/{output_folder}.adoc"))?;
-    let current_chapter = "";
-    for value in all_markup {
-        if current_chapter != value.chapter_number {
-            file.write_all(format!("== Chapter {}\n\n", value.chapter_number).as_ref());
+
+    let document_name = file_naming.document_name(output_folder);
+    let mut body = String::new();
+    let mut current_chapter = String::new();
+    for (_, value) in all_markup {
+        // A combined diff is a patch of each law section, with no headings
+        let heading = if format == MarkupFormat::Diff {
+            None
+        } else if single_doc {
+            // Head each law section, since bill order can revisit a chapter
+            Some(format!(
+                "Chapter {}, Section {}",
+                value.chapter_number, value.section_number
+            ))
+        } else if current_chapter != value.chapter_number {
+            current_chapter = value.chapter_number.clone();
+            Some(format!("Chapter {}", value.chapter_number))
+        } else {
+            None
+        };
+        if let Some(heading) = heading {
+            body.push_str(&format!("{}\n\n", renderer.heading(1, &heading)));
+        }
+        body.push_str(&value.text);
+
+        // Blank lines between diffs would be read as context
+        if format != MarkupFormat::Diff {
+            body.push_str("\n\n");
+        }
+    }
+    write_if_changed(
+        format!("{output_folder}/{document_name}.{extension}"),
+        renderer.document(&document_name, &body).as_ref(),
+    )?;
+    Ok(warnings)
+}
+
+pub fn write_if_changed(path: impl AsRef<Path>, contents: &[u8]) -> io::Result<bool> {
+    // Leave an identical file untouched, so its modification time shows it needs no rendering
+    if fs::read(&path).is_ok_and(|existing| existing == contents) {
+        debug!("{} is unchanged", path.as_ref().display());
+        return Ok(false);
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+pub fn is_rendered(source_path: &Path, output_path: &Path) -> bool {
+    // Rendered output at least as new as its source is current
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(source_path), modified(output_path)) {
+        (Ok(source_modified), Ok(output_modified)) => output_modified >= source_modified,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNaming {
+    // Folder, within the output folder, into which law section files are written
+    pub law_folder: String,
+    pub template: String,
+    // General court, such as "193rd", and bill number, such as "H.4072"
+    pub court: String,
+    pub bill: String,
+}
+
+impl Default for FileNaming {
+    fn default() -> Self {
+        FileNaming {
+            law_folder: String::from(LAW_FOLDER),
+            template: String::from(DEFAULT_FILE_NAME_TEMPLATE),
+            court: String::new(),
+            bill: String::new(),
         }
-        file.write_all(format!("{}\n\n", value.text).as_ref())?;
     }
+}
+
+impl FileNaming {
+    pub fn law_section_name(&self, law_chapter_key: &str) -> String {
+        let (law_chapter, law_section) = law_chapter_key
+            .split_once('-')
+            .unwrap_or((law_chapter_key, ""));
+        let file_name = self
+            .fill_bill_placeholders(&self.template)
+            .replace("{chapter}", &pad_law_number(law_chapter))
+            .replace("{section}", &pad_law_number(law_section))
+            .replace("{key}", law_chapter_key);
+        sanitize_folder_name(&file_name)
+    }
+
+    /// Path, relative to the output folder, of the law section file
+    pub fn law_section_file(&self, law_chapter_key: &str, extension: &str) -> String {
+        format!(
+            "{}/{}.{extension}",
+            self.law_folder,
+            self.law_section_name(law_chapter_key)
+        )
+    }
+
+    pub fn document_name(&self, output_folder: &str) -> String {
+        // Name the combined document with the parts of the template which name no law section,
+        // or else after the output folder itself, not its full path
+        let template = self
+            .template
+            .split('-')
+            .filter(|part| {
+                !LAW_SECTION_PLACEHOLDERS
+                    .iter()
+                    .any(|placeholder| part.contains(placeholder))
+            })
+            .collect::<Vec<_>>()
+            .join("-");
+        let document_name = self.fill_bill_placeholders(&template);
+        if !document_name.trim_matches('-').is_empty() {
+            return sanitize_folder_name(&document_name);
+        }
+        Path::new(output_folder)
+            .file_name()
+            .map_or(output_folder.into(), |name| name.to_string_lossy().into())
+    }
+
+    fn fill_bill_placeholders(&self, template: &str) -> String {
+        let court: String = self
+            .court
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        template
+            .replace("{court}", &court)
+            .replace("{bill}", &self.bill.trim().replace(' ', "_"))
+    }
+}
+
+pub fn pad_law_number(law_number: &str) -> String {
+    // Pad the leading number to three digits, so chapters and sections sort naturally, such as
+    // "006A" before "040"
+    let digit_count = law_number.chars().take_while(char::is_ascii_digit).count();
+    if digit_count == 0 {
+        return law_number.to_string();
+    }
+    format!(
+        "{:0>3}{}",
+        &law_number[..digit_count],
+        &law_number[digit_count..]
+    )
+}
+
+pub fn parse_file_name_template(template: &str) -> Result<String, String> {
+    // Allow only known placeholders, and require one naming the law section, so files differ
+    let mut remainder = template;
+    while let Some(start) = remainder.find('{') {
+        let end = remainder[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in {template:?}"))?;
+        let placeholder = &remainder[start..start + end + 1];
+        if !BILL_PLACEHOLDERS.contains(&placeholder)
+            && !LAW_SECTION_PLACEHOLDERS.contains(&placeholder)
+        {
+            return Err(format!(
+                "Unknown placeholder {placeholder} in {template:?}, use {}",
+                [
+                    BILL_PLACEHOLDERS.as_slice(),
+                    LAW_SECTION_PLACEHOLDERS.as_slice()
+                ]
+                .concat()
+                .join(", ")
+            ));
+        }
+        remainder = &remainder[start + end + 1..];
+    }
+    let names_law_section = |placeholders: &[&str]| {
+        placeholders
+            .iter()
+            .all(|placeholder| template.contains(placeholder))
+    };
+    if !names_law_section(&["{key}"]) && !names_law_section(&["{chapter}", "{section}"]) {
+        return Err(format!(
+            "File name template {template:?} must include {{key}}, or {{chapter}} and {{section}}"
+        ));
+    }
+    Ok(template.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    // Relative to the output folder
+    pub file: String,
+    pub format: String,
+    pub law_chapter_key: String,
+    pub bill_section_keys: Vec<String>,
+    pub law_url: String,
+}
+
+pub fn create_manifest(
+    config: &Config,
+    law_sections_text: &[law_section::LawSectionWithText],
+    output_folder: &str,
+    formats: &[MarkupFormat],
+    file_naming: &FileNaming,
+    single_doc: bool,
+) -> Vec<ManifestEntry> {
+    // List each file into which each law section is written, in each format, including the
+    // combined document
+    let document_name = file_naming.document_name(output_folder);
+    let mut manifest = Vec::new();
+    for law_section in law_sections_text {
+        let (law_chapter, law_section_number) = law_section
+            .law_chapter_key
+            .split_once('-')
+            .unwrap_or((&law_section.law_chapter_key, ""));
+        let law_url = law_section::get_law_url(config, law_chapter, law_section_number).to_string();
+        for format in formats {
+            let extension = format.extension();
+            let mut files = vec![format!("{document_name}.{extension}")];
+            if !single_doc {
+                files.insert(
+                    0,
+                    file_naming.law_section_file(&law_section.law_chapter_key, extension),
+                );
+            }
+            for file in files {
+                manifest.push(ManifestEntry {
+                    file,
+                    format: extension.to_string(),
+                    law_chapter_key: law_section.law_chapter_key.clone(),
+                    bill_section_keys: law_section.bill_section_keys.clone(),
+                    law_url: law_url.clone(),
+                });
+            }
+        }
+    }
+    manifest
+}
+
+pub fn remove_generated_files(
+    manifest: &mut Vec<ManifestEntry>,
+    output_folder: &str,
+    format: MarkupFormat,
+) -> io::Result<()> {
+    // Remove only the files of the format listed as written, and drop them from the manifest
+    let extension = format.extension();
+    for entry in manifest.iter().filter(|entry| entry.format == extension) {
+        match fs::remove_file(Path::new(output_folder).join(&entry.file)) {
+            // The combined document is listed once for each law section in it
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+    manifest.retain(|entry| entry.format != extension);
     Ok(())
 }
 
-pub fn run_asciidoctor(output_folder: String) -> () {
-    let paths = markup::get_adoc_paths(&format!("{output_folder}")).unwrap();
+pub fn copy_generated_files(
+    manifest: &[ManifestEntry],
+    from_folder: &Path,
+    output_folder: &str,
+    format: MarkupFormat,
+) -> io::Result<()> {
+    // Copy the files of the format listed as written, into the same place in the output folder
+    let extension = format.extension();
+    for entry in manifest.iter().filter(|entry| entry.format == extension) {
+        let output_path = Path::new(output_folder).join(&entry.file);
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from_folder.join(&entry.file), output_path)?;
+    }
+    Ok(())
+}
+
+pub fn write_manifest(
+    manifest: &[ManifestEntry],
+    output_folder: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(format!("{output_folder}/{MANIFEST_FILE}"))?;
+    file.write_all(format_manifest_json(manifest).as_bytes())?;
+    Ok(())
+}
+
+pub fn format_manifest_json(manifest: &[ManifestEntry]) -> String {
+    // Write the JSON directly, since the manifest is written whether or not serde is enabled
+    let entries: Vec<String> = manifest
+        .iter()
+        .map(|entry| {
+            let bill_section_keys: Vec<String> = entry
+                .bill_section_keys
+                .iter()
+                .map(|key| format!("\n      {}", quote_json(key)))
+                .collect();
+            let bill_section_keys = if bill_section_keys.is_empty() {
+                String::from("[]")
+            } else {
+                format!("[{}\n    ]", bill_section_keys.join(","))
+            };
+            format!(
+                "  {{\n    \"file\": {},\n    \"format\": {},\n    \"law_chapter_key\": {},\n    \"bill_section_keys\": {bill_section_keys},\n    \"law_url\": {}\n  }}",
+                quote_json(&entry.file),
+                quote_json(&entry.format),
+                quote_json(&entry.law_chapter_key),
+                quote_json(&entry.law_url)
+            )
+        })
+        .collect();
+    if entries.is_empty() {
+        return String::from("[]");
+    }
+    format!("[\n{}\n]", entries.join(",\n"))
+}
+
+fn quote_json(text: &str) -> String {
+    // Quote a JSON string, escaping quotes, backslashes, and control characters
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c < ' ' => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn run_asciidoctor(
+    output_folder: &str,
+    asciidoctor_bin: &str,
+    force: bool,
+) -> Result<(), SpringbokError> {
+    let paths = markup::get_paths_with_extension(output_folder, "adoc", true)?;
 
     for path in paths {
-        Command::new("asciidoctor")
+        // Skip documents rendered since they were last written, unless forced
+        if !force && is_rendered(&path, &path.with_extension("html")) {
+            debug!("{} is already rendered", path.display());
+            continue;
+        }
+        let output = Command::new(asciidoctor_bin)
             .arg(path.as_os_str())
             .output()
-            .expect(
-                "Failed to parse file - is asciidoctor installed? (i.e. ~brew install asciidoctor)",
-            );
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::NotFound => SpringbokError::AsciidoctorMissing {
+                    program: asciidoctor_bin.to_string(),
+                },
+                _ => SpringbokError::io(asciidoctor_bin, error),
+            })?;
+        let messages = format_asciidoctor_messages(&path, &output.stdout, &output.stderr);
+        if !output.status.success() {
+            return Err(SpringbokError::Asciidoctor {
+                program: asciidoctor_bin.to_string(),
+                file: path,
+                status: output.status,
+                messages,
+            });
+        }
+
+        // Asciidoctor warns of malformed markup, but still renders it
+        if let Some(messages) = messages {
+            warn!("{asciidoctor_bin} warned: {messages}");
+        }
+    }
+    Ok(())
+}
+
+pub fn format_asciidoctor_messages(path: &Path, stdout: &[u8], stderr: &[u8]) -> Option<String> {
+    // List each line asciidoctor printed, under the path of the file rendered
+    let output = [stdout, stderr]
+        .iter()
+        .map(|output| String::from_utf8_lossy(output))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let lines: Vec<&str> = output
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.is_empty() {
+        return None;
     }
+    Some(format!("{}\n  {}", path.display(), lines.join("\n  ")))
 }
@@ -1,16 +1,27 @@
-use crate::Cli;
+use crate::{
+    cache::{read_cache, write_cache, Cache},
+    config::{get_site_url, Config},
+    error::SpringbokError,
+    http::fetch,
+    init_cache, Cli,
+};
 use indexmap::IndexMap;
-use log::{debug, info};
+use log::{debug, info, warn};
 use scraper::{ElementRef, Html, Selector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-pub fn get_search_page(cli: &Cli) -> (bool, Url, String) {
+// Whether to search, each general court with its search URL, and the search term
+pub type SearchPage = (bool, Vec<(String, Url)>, String);
+
+pub fn get_search_page(cli: &Cli, config: &Config) -> Result<SearchPage, SpringbokError> {
     // Get default search page, parse, and create refiner map
     info!("Creating refiner map");
-    let refiner_map = create_refiner_map();
+    let refiner_map = create_refiner_map(config, init_cache(cli).as_ref())?;
 
     // Construct search URL
-    let mut search_url = Url::parse("https://malegislature.gov/Bills/Search").unwrap();
+    let mut search_url = get_site_url(config, "Bills/Search");
 
     // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1
     // https://malegislature.gov/Bills/Search?SearchTerms=mbta&Page=1
@@ -30,87 +41,252 @@ pub fn get_search_page(cli: &Cli) -> (bool, Url, String) {
         .append_pair("SearchTerms", search_term.as_str())
         .append_pair("Page", "1");
 
-    // https://malegislature.gov/Bills/Search
-    // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawsgeneralcourt%5D=3139326e64202832303231202d203230323229
-    print_entries_or_append_query_pair(
-        cli.general_court.as_deref(),
-        refiner_map.get("General Court").unwrap(),
-        &mut String::from("Refinements[lawsgeneralcourt]"),
-        &mut search_url,
-    );
-
     // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawsbranchname%5D=486f757365
-    do_search = match print_entries_or_append_query_pair(
-        cli.branch.as_deref(),
-        refiner_map.get("Branch").unwrap(),
-        &mut String::from("Refinements[lawsbranchname]"),
+    do_search = match print_entries_or_append_query_pairs(
+        &cli.branch,
+        get_refiner_group(&refiner_map, "Branch")?,
+        "Refinements[lawsbranchname]",
         &mut search_url,
-    ) {
+        cli.filter.as_deref(),
+    )? {
         None => do_search,
         Some(do_search) => do_search,
     };
 
     // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawsuserprimarysponsorname%5D=4172636965726f2c204a616d6573
-    do_search = match print_entries_or_append_query_pair(
-        cli.sponsor_legislator.as_deref(),
-        refiner_map.get("Sponsor — Legislator").unwrap(),
-        &mut String::from("Refinements[lawsuserprimarysponsorname]"),
+    do_search = match print_entries_or_append_query_pairs(
+        &cli.sponsor_legislator,
+        get_refiner_group(&refiner_map, "Sponsor — Legislator")?,
+        "Refinements[lawsuserprimarysponsorname]",
         &mut search_url,
-    ) {
+        cli.filter.as_deref(),
+    )? {
         None => do_search,
         Some(do_search) => do_search,
     };
 
     // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawscommitteeprimarysponsorname%5D=3139326e64204a52756c6573
-    do_search = match print_entries_or_append_query_pair(
-        cli.sponsor_committee.as_deref(),
-        refiner_map.get("Sponsor — Committee").unwrap(),
-        &mut String::from("Refinements[lawscommitteeprimarysponsorname]"),
+    do_search = match print_entries_or_append_query_pairs(
+        &cli.sponsor_committee,
+        get_refiner_group(&refiner_map, "Sponsor — Committee")?,
+        "Refinements[lawscommitteeprimarysponsorname]",
         &mut search_url,
-    ) {
+        cli.filter.as_deref(),
+    )? {
         None => do_search,
         Some(do_search) => do_search,
     };
 
     // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawsotherprimarysponsorname%5D=41756469746f72206f662074686520436f6d6d6f6e7765616c7468
-    do_search = match print_entries_or_append_query_pair(
-        cli.sponsor_other.as_deref(),
-        refiner_map.get("Sponsor — Other").unwrap(),
-        &mut String::from("Refinements[lawsotherprimarysponsorname]"),
+    do_search = match print_entries_or_append_query_pairs(
+        &cli.sponsor_other,
+        get_refiner_group(&refiner_map, "Sponsor — Other")?,
+        "Refinements[lawsotherprimarysponsorname]",
         &mut search_url,
-    ) {
+        cli.filter.as_deref(),
+    )? {
         None => do_search,
         Some(do_search) => do_search,
     };
 
     // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawsfilingtype%5D=416d656e646d6%%6e74
-    do_search = match print_entries_or_append_query_pair(
-        cli.document_type.as_deref(),
-        refiner_map.get("Document Type").unwrap(),
-        &mut String::from("Refinements[lawsfilingtype]"),
+    do_search = match print_entries_or_append_query_pairs(
+        &cli.document_type,
+        get_refiner_group(&refiner_map, "Document Type")?,
+        "Refinements[lawsfilingtype]",
         &mut search_url,
-    ) {
+        cli.filter.as_deref(),
+    )? {
         None => do_search,
         Some(do_search) => do_search,
     };
-    (do_search, search_url, search_term)
+    // The search page has no filing date refiner, so search results are filtered by the filing
+    // date in each row once found, and the dates only need to be consistent here
+    if let (Some(filed_after), Some(filed_before)) = (&cli.filed_after, &cli.filed_before) {
+        if filed_after > filed_before {
+            return Err(SpringbokError::invalid_argument(format!(
+                "Filed after {filed_after} is later than filed before {filed_before}"
+            )));
+        }
+    }
+
+    // https://malegislature.gov/Bills/Search
+    // https://malegislature.gov/Bills/Search?SearchTerms=&Page=1&Refinements%5Blawsgeneralcourt%5D=3139326e64202832303231202d203230323229
+    let general_court_group = get_refiner_group(&refiner_map, "General Court")?;
+    if cli.general_court.len() < 2 || cli.general_court.iter().any(|c| c == "MISSING") {
+        print_entries_or_append_query_pairs(
+            cli.general_court.as_slice(),
+            general_court_group,
+            "Refinements[lawsgeneralcourt]",
+            &mut search_url,
+            cli.filter.as_deref(),
+        )?;
+        let general_court = cli.general_court.first().cloned().unwrap_or_default();
+        return Ok((do_search, vec![(general_court, search_url)], search_term));
+    }
+
+    // Search each general court separately, so each result can be attributed to its court
+    let mut search_urls = Vec::new();
+    for general_court in &cli.general_court {
+        let mut court_search_url = search_url.clone();
+        print_entries_or_append_query_pairs(
+            std::slice::from_ref(general_court),
+            general_court_group,
+            "Refinements[lawsgeneralcourt]",
+            &mut court_search_url,
+            cli.filter.as_deref(),
+        )?;
+        search_urls.push((general_court.clone(), court_search_url));
+    }
+    Ok((do_search, search_urls, search_term))
+}
+
+pub fn parse_filing_date(filing_date: &str) -> Result<String, String> {
+    // Accept only dates formatted as YYYY-MM-DD
+    let error = || format!("{filing_date:?} is not a date formatted as YYYY-MM-DD");
+    let parts: Vec<&str> = filing_date.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(error());
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return Err(error());
+    }
+    let (Ok(_), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return Err(error());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(error());
+    }
+    Ok(filing_date.to_string())
+}
+
+fn get_refiner_group<'a>(
+    refiner_map: &'a IndexMap<String, IndexMap<String, RefinerEntry>>,
+    group_label: &str,
+) -> Result<&'a IndexMap<String, RefinerEntry>, SpringbokError> {
+    refiner_map
+        .get(group_label)
+        .ok_or_else(|| SpringbokError::parse(format!("Cannot find refiner group {group_label:?}")))
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RefinerEntry {
     pub refiner_label: String,
     pub refiner_token: String,
 }
-pub fn create_refiner_map() -> IndexMap<String, IndexMap<String, RefinerEntry>> {
-    // Use an IndexMap to preserve order
-    let mut refiner_map = IndexMap::new();
+
+pub fn create_refiner_map(
+    config: &Config,
+    cache: Option<&Cache>,
+) -> Result<IndexMap<String, IndexMap<String, RefinerEntry>>, SpringbokError> {
+    fetch_refiner_map(
+        config,
+        cache,
+        &get_site_url(config, "Bills/Search?SearchTerms=&Page=1"),
+        "refiners.tsv",
+    )
+}
+
+pub fn create_general_court_refiner_map(
+    config: &Config,
+    cache: Option<&Cache>,
+    general_court: &str,
+) -> Result<IndexMap<String, IndexMap<String, RefinerEntry>>, SpringbokError> {
+    // Refine the search page by general court, so only its refiners are listed
+    let refiner_map = create_refiner_map(config, cache)?;
+    let mut search_url = get_site_url(config, "Bills/Search?SearchTerms=&Page=1");
+    print_entries_or_append_query_pairs(
+        &[general_court.to_string()],
+        get_refiner_group(&refiner_map, "General Court")?,
+        "Refinements[lawsgeneralcourt]",
+        &mut search_url,
+        None,
+    )?;
+    fetch_refiner_map(
+        config,
+        cache,
+        &search_url,
+        &format!("refiners-{general_court}.tsv"),
+    )
+}
+
+fn fetch_refiner_map(
+    config: &Config,
+    cache: Option<&Cache>,
+    search_url: &Url,
+    cache_name: &str,
+) -> Result<IndexMap<String, IndexMap<String, RefinerEntry>>, SpringbokError> {
+    // Use the cached refiner map, if present and fresh
+    if let Some(refiner_cache) = cache.and_then(|cache| read_cache(cache, cache_name)) {
+        match parse_refiner_cache(&refiner_cache) {
+            Some(refiner_map) => return Ok(refiner_map),
+            None => warn!("Couldn't parse cached refiners in {cache_name}"),
+        }
+    }
 
     // Get the page from which to parse refiners
-    let body = reqwest::blocking::get("https://malegislature.gov/Bills/Search?SearchTerms=&Page=1")
-        .unwrap()
-        .text()
-        .unwrap();
-    let document = Html::parse_document(body.as_str());
+    let body = fetch(config, search_url)?;
+    let refiner_map = parse_refiner_map(&body)?;
+    if let Some(cache) = cache {
+        write_cache(cache, cache_name, &format_refiner_cache(&refiner_map));
+    }
+    Ok(refiner_map)
+}
+
+pub fn format_refiner_cache(
+    refiner_map: &IndexMap<String, IndexMap<String, RefinerEntry>>,
+) -> String {
+    // One refiner per line, as its group label, refiner label, and token, separated by tabs,
+    // in the order parsed
+    let mut refiner_cache = String::new();
+    for (group_label, refiner_group_map) in refiner_map {
+        for refiner_entry in refiner_group_map.values() {
+            refiner_cache.push_str(&format!(
+                "{group_label}\t{}\t{}\n",
+                refiner_entry.refiner_label, refiner_entry.refiner_token
+            ));
+        }
+    }
+    refiner_cache
+}
+
+fn parse_refiner_cache(
+    refiner_cache: &str,
+) -> Option<IndexMap<String, IndexMap<String, RefinerEntry>>> {
+    // Any line without three fields means the cache is unusable
+    let mut refiner_map: IndexMap<String, IndexMap<String, RefinerEntry>> = IndexMap::new();
+    for line in refiner_cache.lines() {
+        let mut fields = line.split('\t');
+        let (Some(group_label), Some(refiner_label), Some(refiner_token), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return None;
+        };
+        refiner_map
+            .entry(group_label.to_string())
+            .or_default()
+            .insert(
+                refiner_label.to_string(),
+                RefinerEntry {
+                    refiner_label: refiner_label.to_string(),
+                    refiner_token: refiner_token.to_string(),
+                },
+            );
+    }
+    Some(refiner_map)
+}
+
+pub fn parse_refiner_map(
+    body: &str,
+) -> Result<IndexMap<String, IndexMap<String, RefinerEntry>>, SpringbokError> {
+    // Use an IndexMap to preserve order
+    let mut refiner_map = IndexMap::new();
+    let document = Html::parse_document(body);
 
     // Define all selectors required to select the refiners
     let refiner_selector = Selector::parse("div#refiners").unwrap();
@@ -122,7 +298,15 @@ pub fn create_refiner_map() -> IndexMap<String, IndexMap<String, RefinerEntry>>
     let input_selector = Selector::parse("input").unwrap();
 
     // Find the div#refiners element which contains all refiner groups, then consider each group
-    let refiner_element = document.select(&refiner_selector).next().unwrap();
+    let refiner_element =
+        document
+            .select(&refiner_selector)
+            .next()
+            .ok_or_else(|| SpringbokError::Selector {
+                element: String::from("refiners"),
+                selectors: vec![String::from("div#refiners")],
+                url: None,
+            })?;
     for group_element in refiner_element.select(&group_selector) {
         // Use an IndexMap to preserve order
         let mut refiner_group_map = IndexMap::new();
@@ -131,160 +315,526 @@ pub fn create_refiner_map() -> IndexMap<String, IndexMap<String, RefinerEntry>>
         let group_label_element: ElementRef = match group_element.select(&title_selector).next() {
             None => {
                 // ... but if it isn't there, find it in the fieldset element
-                group_element.select(&legend_selector).next().unwrap()
+                group_element
+                    .select(&legend_selector)
+                    .next()
+                    .ok_or_else(|| {
+                        SpringbokError::parse("Cannot find refiner group title or legend")
+                    })?
             }
             Some(element) => element,
         };
-        let group_label = String::from(group_label_element.text().collect::<Vec<_>>()[0].trim());
+        let group_label = get_text(&group_label_element, 0)
+            .ok_or_else(|| SpringbokError::parse("Cannot find refiner group label text"))?
+            .trim()
+            .to_string();
         debug!("\nGroup label: {:?}", group_label);
 
         // Find the div.modal-body element ...
         let group_column_element = match group_element.select(&body_selector).next() {
             None => {
-                // ... but if it isn't there, clone the fieldset element, then consider each row label element
-                group_element.clone()
+                // ... but if it isn't there, use the fieldset element, then consider each row label element
+                group_element
             }
             Some(element) => element,
         };
         for row_label_element in group_column_element.select(&label_selector) {
             // Assign label for this refiner
-            let refiner_label = row_label_element.text().collect::<Vec<_>>()[1]
+            let refiner_label = get_text(&row_label_element, 1)
+                .ok_or_else(|| {
+                    SpringbokError::parse(format!(
+                        "Cannot find refiner label text in group {group_label:?}"
+                    ))
+                })?
                 .trim()
                 .replace("  ", " ");
             debug!("Refiner label: {:?}", refiner_label);
 
             // Create a unique key from the label
-            let mut refiner_key: String =
-                String::from(refiner_label.split(' ').collect::<Vec<&str>>()[0])
-                    .trim_end_matches(",")
-                    .parse::<String>()
-                    .unwrap()
-                    .replace("'", "-");
+            let mut refiner_key: String = refiner_label.split(' ').collect::<Vec<&str>>()[0]
+                .trim_end_matches(',')
+                .replace('\'', "-");
             if !(group_label.contains("Court")
                 || group_label.contains("Branch")
                 || group_label.contains("Legislator"))
             {
-                let words = refiner_label.split(" ").collect::<Vec<&str>>();
+                let words = refiner_label.split(' ').collect::<Vec<&str>>();
                 refiner_key = words[..words.len() - 1]
                     .join("-")
-                    .replace("/", "-")
-                    .replace("(", "")
-                    .replace(")", "")
-                    .replace("'", "")
-                    .replace(",", "")
-                    .replace(".", "");
+                    .replace('/', "-")
+                    .replace(['(', ')', '\'', ',', '.'], "");
             }
             debug!("Refiner key: {:?}", refiner_key);
 
             // Find the token for this entry
-            let input_element = row_label_element.select(&input_selector).next().unwrap();
-            let refiner_token = input_element.value().attr("data-refinertoken").unwrap();
+            let refiner_token = row_label_element
+                .select(&input_selector)
+                .next()
+                .and_then(|input_element| input_element.value().attr("data-refinertoken"))
+                .ok_or_else(|| {
+                    SpringbokError::parse(format!(
+                        "Cannot find refiner token for {refiner_label:?}"
+                    ))
+                })?;
             debug!("Refiner token: {}", refiner_token);
 
             // Collect each refiner group entry key, label, and token
             refiner_group_map.insert(
-                String::from(refiner_key),
+                refiner_key,
                 RefinerEntry {
-                    refiner_label: String::from(refiner_label),
+                    refiner_label,
                     refiner_token: String::from(refiner_token),
                 },
             );
         }
-        refiner_map.insert(String::from(group_label), refiner_group_map);
+        refiner_map.insert(group_label, refiner_group_map);
     }
-    refiner_map
+    Ok(refiner_map)
 }
 
-pub fn print_entries_or_append_query_pair(
-    argument: Option<&str>,
-    refiner_group_map: &IndexMap<String, RefinerEntry>,
-    refiner_field: &mut String,
-    search_url: &mut Url,
-) -> Option<bool> {
-    match argument {
-        Some(refiner_key) if refiner_key == &String::from("MISSING") => {
-            // Refiner key is missing, so list all possible keys
-            for (refiner_key, refiner_entry) in refiner_group_map.iter() {
-                println!(
-                    r#"Use "{}" for "{}""#,
-                    refiner_key, refiner_entry.refiner_label
-                );
+pub fn create_refiner_listing(
+    cli: &Cli,
+    config: &Config,
+) -> Result<Option<String>, SpringbokError> {
+    // Each refiner group which was asked to be listed
+    let group_labels: Vec<&str> = [
+        (cli.list_committees, "Sponsor — Committee"),
+        (cli.list_legislators, "Sponsor — Legislator"),
+        (cli.list_document_types, "Document Type"),
+    ]
+    .into_iter()
+    .filter_map(|(is_listed, group_label)| is_listed.then_some(group_label))
+    .collect();
+    if group_labels.is_empty() {
+        return Ok(None);
+    }
+
+    // List the refiners of the first general court given
+    let general_court = cli
+        .general_court
+        .iter()
+        .find(|general_court| *general_court != "MISSING")
+        .ok_or_else(|| {
+            SpringbokError::invalid_argument(
+                "Give a general court, such as --general-court 193rd, to list refiners for",
+            )
+        })?;
+    let refiner_map =
+        create_general_court_refiner_map(config, init_cache(cli).as_ref(), general_court)?;
+
+    // Head each table with its group when listing more than one
+    let mut refiner_listing = String::new();
+    for group_label in &group_labels {
+        let refiner_table = format_refiner_table(
+            get_refiner_group(&refiner_map, group_label)?,
+            cli.filter.as_deref(),
+        );
+        if group_labels.len() > 1 {
+            if !refiner_listing.is_empty() {
+                refiner_listing.push('\n');
             }
-            None
-        }
-        Some(refiner_key) => {
-            // Refiner key is not missing, so append the query pair
-            search_url.query_pairs_mut().append_pair(
-                refiner_field,
-                refiner_group_map
-                    .get(refiner_key)
-                    .unwrap()
-                    .refiner_token
-                    .as_str(),
-            );
-            Some(true)
+            refiner_listing.push_str(&format!("{group_label} ({general_court})\n\n"));
         }
-        None => None,
+        refiner_listing.push_str(&refiner_table);
     }
+    Ok(Some(refiner_listing))
+}
+
+pub fn print_entries_or_append_query_pairs(
+    arguments: &[String],
+    refiner_group_map: &IndexMap<String, RefinerEntry>,
+    refiner_field: &str,
+    search_url: &mut Url,
+    filter: Option<&str>,
+) -> Result<Option<bool>, SpringbokError> {
+    if arguments.iter().any(|argument| argument == "MISSING") {
+        // Refiner key is missing, so list all possible keys, or those matching the filter
+        print!("{}", format_refiner_table(refiner_group_map, filter));
+        return Ok(None);
+    }
+    if arguments.is_empty() {
+        return Ok(None);
+    }
+    for refiner_key in arguments {
+        // Refiner key is not missing, so append one query pair for each value, or list all
+        // possible keys if the value is not one of them
+        let refiner_entry = refiner_group_map.get(refiner_key).ok_or_else(|| {
+            SpringbokError::invalid_argument(format!(
+                "Unknown value {refiner_key:?} for {refiner_field}, use one of\n{}",
+                format_refiner_table(refiner_group_map, None).trim_end()
+            ))
+        })?;
+        search_url
+            .query_pairs_mut()
+            .append_pair(refiner_field, refiner_entry.refiner_token.as_str());
+    }
+    Ok(Some(true))
+}
+
+pub fn format_refiner_table(
+    refiner_group_map: &IndexMap<String, RefinerEntry>,
+    filter: Option<&str>,
+) -> String {
+    // Keep entries whose key or label contains the filter, ignoring case
+    let filter = filter.map(|filter| filter.to_lowercase());
+    let mut refiner_entries: Vec<(&String, &RefinerEntry)> = refiner_group_map
+        .iter()
+        .filter(|(refiner_key, refiner_entry)| {
+            filter.as_ref().is_none_or(|filter| {
+                refiner_key.to_lowercase().contains(filter)
+                    || refiner_entry.refiner_label.to_lowercase().contains(filter)
+            })
+        })
+        .collect();
+    if refiner_entries.is_empty() {
+        return String::from("No values matched\n");
+    }
+    refiner_entries.sort_by_key(|(refiner_key, _)| refiner_key.to_lowercase());
+
+    // Align labels after the longest key
+    let key_width = refiner_entries
+        .iter()
+        .map(|(refiner_key, _)| refiner_key.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("Key".len());
+    let mut refiner_table = format!("{:key_width$}  Label\n", "Key");
+    refiner_table.push_str(&format!("{}  -----\n", "-".repeat(key_width)));
+    for (refiner_key, refiner_entry) in refiner_entries {
+        refiner_table.push_str(&format!(
+            "{refiner_key:key_width$}  {}\n",
+            refiner_entry.refiner_label
+        ));
+    }
+    refiner_table
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SearchEntry {
     pub bill_url: Url,
     pub bill_sponsor: String,
     pub bill_summary: String,
+    pub doc_type: DocType,
+    // Formatted as YYYY-MM-DD, when the search results table gives a filing date
+    pub filed_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DocType {
+    Bill,
+    Amendment,
+}
+
+pub fn get_doc_type(url: &Url) -> DocType {
+    // Amendment URLs have the form https://malegislature.gov/Bills/193/H4800/Amendments/House/12
+    let is_amendment = url.path_segments().is_some_and(|mut segments| {
+        segments.any(|segment| segment.to_lowercase().starts_with("amendment"))
+    });
+    if is_amendment {
+        DocType::Amendment
+    } else {
+        DocType::Bill
+    }
+}
+
+pub fn get_general_court(bill_url: &Url) -> String {
+    // Bill URLs have the form https://malegislature.gov/Bills/193/H4072
+    bill_url
+        .path_segments()
+        .and_then(|mut segments| segments.nth(1))
+        .unwrap_or_default()
+        .to_string()
+}
+
+pub fn get_bill_url(
+    config: &Config,
+    general_court: &str,
+    bill_number: &str,
+) -> Result<Url, SpringbokError> {
+    // https://malegislature.gov/Bills/193/H4607
+    let court_number: String = general_court
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let bill_id = bill_number.trim().replace('.', "").to_uppercase();
+    let letters = bill_id.trim_end_matches(|c: char| c.is_ascii_digit());
+    if court_number.is_empty()
+        || letters.is_empty()
+        || letters.len() == bill_id.len()
+        || !letters.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return Err(SpringbokError::parse(format!(
+            "Cannot find bill {bill_number:?} in general court {general_court:?}, use a bill number such as H.4607 and a general court such as 193rd"
+        )));
+    }
+    Ok(get_site_url(
+        config,
+        &format!("Bills/{court_number}/{bill_id}"),
+    ))
+}
+
+pub fn get_and_print_search_results(
+    config: &Config,
+    url: &Url,
+    max_pages: usize,
+) -> Result<IndexMap<String, SearchEntry>, SpringbokError> {
+    let search_results_map = get_search_results(config, url, max_pages)?;
+    print_search_results(&search_results_map);
+    Ok(search_results_map)
+}
+
+pub fn get_search_results(
+    config: &Config,
+    url: &Url,
+    max_pages: usize,
+) -> Result<IndexMap<String, SearchEntry>, SpringbokError> {
+    // Use an IndexMap to preserve order
+    let mut search_results_map = IndexMap::new();
+
+    // Get each search result page, and parse each result row, until the last page is reached
+    let mut page_count = 1;
+    let mut page = 1;
+    while page <= page_count.min(max_pages) {
+        let page_url = get_page_url(url, page);
+        info!("Value for search page URL: {page_url}");
+        let body = fetch(config, &page_url)?;
+        if page == 1 {
+            page_count = parse_page_count(&body);
+            debug!("Search result page count: {page_count}");
+        }
+        let page_results_map = parse_search_results(&body, &config.base_url)?;
+        if page_results_map.is_empty() {
+            break;
+        }
+        search_results_map.extend(page_results_map);
+        page += 1;
+    }
+    Ok(search_results_map)
 }
-pub fn get_and_print_search_results(url: &Url) -> IndexMap<String, SearchEntry> {
+
+pub fn print_search_results(search_results_map: &IndexMap<String, SearchEntry>) {
+    // Report no matches, rather than print a header alone
+    if search_results_map.is_empty() {
+        println!("No bills matched");
+        return;
+    }
+    println!("Court — Bill — Link — Sponsor — Summary");
+    for (bill_number, search_entry) in search_results_map {
+        println!(
+            "{} — {bill_number} — {} — {} — {}",
+            get_general_court(&search_entry.bill_url),
+            search_entry.bill_url,
+            search_entry.bill_sponsor,
+            search_entry.bill_summary
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+pub fn get_search_results_json(
+    search_results_map: &IndexMap<String, SearchEntry>,
+) -> serde_json::Value {
+    // Give the same fields as the table, with the URL as a plain string, for tools such as jq
+    search_results_map
+        .iter()
+        .map(|(bill_number, search_entry)| {
+            serde_json::json!({
+                "bill_number": bill_number,
+                "general_court": get_general_court(&search_entry.bill_url),
+                "bill_url": search_entry.bill_url.as_str(),
+                "bill_sponsor": search_entry.bill_sponsor,
+                "bill_summary": search_entry.bill_summary,
+                "doc_type": search_entry.doc_type,
+                "filed_date": search_entry.filed_date,
+            })
+        })
+        .collect()
+}
+
+pub fn get_page_url(url: &Url, page: usize) -> Url {
+    // Replace the page query pair, keeping all others in order
+    let mut page_url = url.clone();
+    let query_pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "Page")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    page_url
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(query_pairs)
+        .append_pair("Page", page.to_string().as_str());
+    page_url
+}
+
+pub fn parse_page_count(body: &str) -> usize {
+    // Find the largest page number linked from the pagination control, if any
+    let document = Html::parse_document(body);
+    let page_selector = Selector::parse("ul.pagination li a").unwrap();
+    document
+        .select(&page_selector)
+        .filter_map(|page_element| {
+            page_element
+                .text()
+                .collect::<String>()
+                .trim()
+                .parse::<usize>()
+                .ok()
+        })
+        .max()
+        .unwrap_or(1)
+}
+
+pub fn parse_search_results(
+    body: &str,
+    base_url: &Url,
+) -> Result<IndexMap<String, SearchEntry>, SpringbokError> {
     // Use an IndexMap to preserve order
     let mut search_results_map = IndexMap::new();
 
-    // Get the search result page, select the table, and parse each result row
-    let body = reqwest::blocking::get(url.clone()).unwrap().text().unwrap();
-    let document = Html::parse_document(body.as_str());
+    // Select the table, and parse each result row
+    let document = Html::parse_document(body);
     let table_body_selector = Selector::parse("tbody").unwrap();
     let table_row_selector = Selector::parse("tr").unwrap();
-    let table_body_element = document.select(&table_body_selector).next().unwrap();
-    println!("Bill — Link — Sponsor — Summary");
-    // TODO: Handle paging?
+    let Some(table_body_element) = document.select(&table_body_selector).next() else {
+        // The page shows a message instead of a table when no bills match
+        debug!("Cannot find search results table using selector \"tbody\"");
+        return Ok(search_results_map);
+    };
+    let filed_date_cell = find_filed_date_cell(&document);
+    if filed_date_cell.is_none() {
+        debug!("Cannot find a filing date column in search results");
+    }
     for table_row_element in table_body_element.select(&table_row_selector) {
-        let (bill_number, bill_url) = get_cell_data(&table_row_element, 2);
-        let (bill_sponsor, _) = get_cell_data(&table_row_element, 3);
-        let (bill_summary, _) = get_cell_data(&table_row_element, 4);
-        println!("{bill_number} — {bill_url} — {bill_sponsor} — {bill_summary}");
+        let (bill_number, bill_url) = get_cell_data(&table_row_element, 2, base_url)?;
+        let (bill_sponsor, _) = get_cell_data(&table_row_element, 3, base_url)?;
+        let (bill_summary, _) = get_cell_data(&table_row_element, 4, base_url)?;
+        // The table may not give a filing date, in which case no date is parsed
+        let filed_date = filed_date_cell
+            .and_then(|cell| get_cell_data(&table_row_element, cell, base_url).ok())
+            .and_then(|(filed_date, _)| parse_table_date(&filed_date));
 
-        // Collect each search result bill number, url, sponsor, and summary
+        // Collect each search result bill number, url, sponsor, summary, and filing date
         search_results_map.insert(
             bill_number,
             SearchEntry {
+                doc_type: get_doc_type(&bill_url),
                 bill_url,
                 bill_sponsor,
                 bill_summary,
+                filed_date,
             },
         );
     }
+    Ok(search_results_map)
+}
+
+fn find_filed_date_cell(document: &Html) -> Option<i32> {
+    // Find the filing date column by its header, such as "Date Filed", counting from one
+    let header_selector = Selector::parse("thead th").unwrap();
+    document
+        .select(&header_selector)
+        .position(|header_element| {
+            let header = header_element.text().collect::<String>().to_lowercase();
+            header.contains("filed") || header.contains("date")
+        })
+        .map(|position| position as i32 + 1)
+}
+
+pub fn parse_table_date(table_date: &str) -> Option<String> {
+    // Accept dates formatted as M/D/YYYY, as the site shows them, or YYYY-MM-DD
+    let table_date = table_date.trim();
+    let filing_date = match table_date.split('/').collect::<Vec<_>>()[..] {
+        [month, day, year] => format!("{year}-{month:0>2}-{day:0>2}"),
+        _ => table_date.to_string(),
+    };
+    parse_filing_date(&filing_date).ok()
+}
+
+pub fn filter_search_results_by_date(
+    search_results_map: IndexMap<String, SearchEntry>,
+    filed_after: Option<&str>,
+    filed_before: Option<&str>,
+) -> IndexMap<String, SearchEntry> {
+    // Keep results filed within the dates, and any without a filing date, which cannot be
+    // filtered, warning that they are kept, since the search itself is not refined by date
+    if filed_after.is_none() && filed_before.is_none() {
+        return search_results_map;
+    }
+    let undated_count = search_results_map
+        .values()
+        .filter(|search_entry| search_entry.filed_date.is_none())
+        .count();
+    if undated_count == search_results_map.len() && undated_count > 0 {
+        warn!("Cannot filter by filing date, since no search result lists one, so all are kept");
+    } else if undated_count > 0 {
+        warn!("{undated_count} search result(s) list no filing date, so are kept unfiltered");
+    }
     search_results_map
+        .into_iter()
+        .filter(|(_, search_entry)| {
+            let Some(filed_date) = search_entry.filed_date.as_deref() else {
+                return true;
+            };
+            filed_after.is_none_or(|filed_after| filed_date >= filed_after)
+                && filed_before.is_none_or(|filed_before| filed_date <= filed_before)
+        })
+        .collect()
 }
 
-pub fn get_cell_data(table_row_element: &ElementRef, cell: i32) -> (String, Url) {
+pub fn get_cell_data(
+    table_row_element: &ElementRef,
+    cell: i32,
+    base_url: &Url,
+) -> Result<(String, Url), SpringbokError> {
     // Most cell elements contains a hyperlink element ...
-    let base_url = Url::parse("https://malegislature.gov").unwrap();
     let mut cell_selector = Selector::parse(format!("td:nth-child({cell}) a").as_str()).unwrap();
     match table_row_element.select(&cell_selector).next() {
         None => {
             // ... but if not, use the cell element, otherwise ...
             cell_selector = Selector::parse(format!("td:nth-child({cell})").as_str()).unwrap();
-            let cell_element = table_row_element.select(&cell_selector).next().unwrap();
-            (
-                String::from(cell_element.text().collect::<Vec<_>>()[0].trim()),
-                base_url,
-            )
+            let cell_element =
+                table_row_element
+                    .select(&cell_selector)
+                    .next()
+                    .ok_or_else(|| {
+                        SpringbokError::parse(format!(
+                            "Cannot find cell {cell} in search results row"
+                        ))
+                    })?;
+            Ok((
+                get_text(&cell_element, 0)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                base_url.clone(),
+            ))
         }
-        Some(cell_element) => (
+        Some(cell_element) => {
             // ... use the hyperlink element
-            String::from(cell_element.text().collect::<Vec<_>>()[0].trim()),
-            base_url
-                .join(cell_element.value().attr("href").unwrap())
-                .unwrap(),
-        ),
+            let href = cell_element.value().attr("href").ok_or_else(|| {
+                SpringbokError::parse(format!(
+                    "Cannot find link in cell {cell} of search results row"
+                ))
+            })?;
+            Ok((
+                get_text(&cell_element, 0)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                base_url.join(href).map_err(|error| {
+                    SpringbokError::parse(format!(
+                        "Invalid link {href:?} in search results row: {error}"
+                    ))
+                })?,
+            ))
+        }
     }
 }
+
+fn get_text<'a>(element: &ElementRef<'a>, index: usize) -> Option<&'a str> {
+    // Select the text node at the index, if present
+    element.text().nth(index)
+}
@@ -1,43 +1,390 @@
 use clap::Parser;
-use indexmap::IndexMap;
-use log::info;
+use log::{info, warn};
 use springbok_mgl::*;
-use std::string::String;
-
-fn main() {
-    // Init logger
-    env_logger::init();
+use std::{
+    error::Error,
+    fs,
+    io::{self, IsTerminal},
+    path::Path,
+    process::ExitCode,
+    sync::mpsc,
+};
 
+fn main() -> ExitCode {
     // Parse CLI
     let cli = Cli::parse();
 
+    // Init logger, letting RUST_LOG override the level given by --quiet or --verbose
+    env_logger::Builder::new()
+        .filter_level(get_log_level(&cli))
+        .parse_default_env()
+        .init();
+
+    // Run, and report any error, or any law section which failed, before exiting non-zero
+    match run(cli) {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(failure_count) => {
+            eprintln!(
+                "Error: {failure_count} bill(s) or law section(s) failed to download or mark up"
+            );
+            ExitCode::FAILURE
+        }
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<usize, Box<dyn Error>> {
+    let config = init_config(&cli)?;
+    let mut warnings = Vec::new();
+    let result = run_stages(&cli, &config, &mut warnings);
+
+    // Summarize the warnings met along the way, which are easily lost in a long run
+    if !warnings.is_empty() {
+        eprint!("Warnings:\n{}", format_warning_summary(&warnings));
+    }
+    let failure_count = result?;
+
+    // Report a run cut short by the deadline, once what completed has been written
+    if let Some(deadline) = cli.deadline {
+        if is_past_deadline(&config) {
+            return Err(
+                format!("Deadline of {deadline} seconds exceeded, so output is partial").into(),
+            );
+        }
+    }
+    Ok(failure_count)
+}
+
+fn run_stages(
+    cli: &Cli,
+    config: &Config,
+    warnings: &mut Vec<Warning>,
+) -> Result<usize, Box<dyn Error>> {
+    if cli.doctor {
+        // Report each selector which no longer matches, since the site layout changed
+        let checks = run_doctor(config);
+        print!("{}", format_selector_checks(&checks));
+        let failed_count = checks.iter().filter(|check| !check.passed()).count();
+        if failed_count > 0 {
+            return Err(format!(
+                "{failed_count} of {} selectors failed, so the legislature website may have changed",
+                checks.len()
+            )
+            .into());
+        }
+        return Ok(0);
+    }
+
+    if let Some(bill_file) = &cli.bill_file {
+        // Create bill struct from a saved bill text page, instead of searching
+        let html = fs::read_to_string(bill_file)
+            .map_err(|error| format!("Couldn't read {}: {}", bill_file.display(), error))?;
+        let (mut bill, section_counts) = create_bill_from_html(&html)?;
+
+        // A saved bill text page carries no metadata beyond what the file is named
+        bill.bill_number = bill_file
+            .file_stem()
+            .map_or(String::from("bill"), |stem| stem.to_string_lossy().into());
+        print_section_counts(cli, &bill, section_counts)?;
+        return write_outputs(cli, config, &bill, warnings);
+    }
+
+    if cli.stdin {
+        // Create bill struct from text nodes captured elsewhere, instead of searching
+        let text_nodes = read_text_nodes(io::stdin().lock())
+            .map_err(|error| format!("Couldn't read stdin: {error}"))?;
+        let (mut bill, section_counts) = create_bill_from_text_nodes(&text_nodes);
+        bill.bill_number = String::from("bill");
+        print_section_counts(cli, &bill, section_counts)?;
+        return write_outputs(cli, config, &bill, warnings);
+    }
+
+    let bill_numbers = get_bill_numbers(cli)?;
+    if let [bill_number] = &bill_numbers[..] {
+        let general_court = cli.general_court.first().map_or("", String::as_str);
+        let (bill, section_counts) =
+            create_bill_from_bill_number(config, general_court, bill_number)?;
+        print_section_counts(cli, &bill, section_counts)?;
+        return write_outputs(cli, config, &bill, warnings);
+    }
+    if !bill_numbers.is_empty() {
+        return run_bills(cli, config, bill_numbers, warnings);
+    }
+
+    // List refiners for the general court, instead of searching
+    if let Some(refiner_listing) = create_refiner_listing(cli, config)? {
+        print!("{refiner_listing}");
+        return Ok(0);
+    }
+
     // Get search results in map and search_term
-    let (search_results_map, search_term) = create_search_results_map(&cli);
+    let (search_results_map, search_term) = create_search_results_map(cli, config)?;
 
-    if cli.download {
-        // Get and print bill text when searching by bill number
-        if let Some(&ref search_entry) = search_results_map.get(search_term.as_str()).as_deref() {
+    if let Some(csv_path) = &cli.csv {
+        // Count sections of each bill in the search results
+        let mut bills_section_counts = Vec::new();
+        for (bill_number, search_entry) in &search_results_map {
+            if search_entry.doc_type == DocType::Amendment {
+                warn!(
+                    "Skipping amendment {bill_number}: {}",
+                    search_entry.bill_url
+                );
+                continue;
+            }
+            if is_past_deadline(config) {
+                warn!("Deadline exceeded, so not counting sections of {bill_number}");
+                break;
+            }
+            let (_, section_counts) = create_bill(config, bill_number, search_entry)?;
+            bills_section_counts.push((bill_number.clone(), section_counts));
+        }
+        write_section_counts_csv(&bills_section_counts, csv_path)?;
+    }
+
+    let mut failure_count = 0;
+    if cli.download || cli.counts_only {
+        // Get and print bill text when searching by bill number, in each court searched
+        let search_entries: Vec<(&String, &SearchEntry)> = search_results_map
+            .iter()
+            .filter(|(key, _)| {
+                **key == search_term
+                    || key
+                        .split_once(':')
+                        .is_some_and(|(_, bill_number)| bill_number == search_term)
+            })
+            .collect();
+        if search_entries.is_empty() {
+            info!("Search term is not a bill number")
+        }
+        for (bill_number, search_entry) in search_entries {
+            check_deadline(config, &format!("downloading {bill_number}"))?;
             // Create bill struct
-            let bill = create_bill(search_entry);
+            let (bill, section_counts) = create_bill(config, bill_number, search_entry)?;
+            print_section_counts(cli, &bill, section_counts)?;
+            failure_count += write_outputs(cli, config, &bill, warnings)?;
+        }
+    }
+    Ok(failure_count)
+}
+
+fn run_bills(
+    cli: &Cli,
+    config: &Config,
+    bill_numbers: Vec<String>,
+    warnings: &mut Vec<Warning>,
+) -> Result<usize, Box<dyn Error>> {
+    // Process bills concurrently, each into its own folder, sharing the config, so every bill
+    // uses the same HTTP client and rate limiter, and requests stay under the rate limit
+    type BillResult = Result<(SectionCounts, usize, Vec<Warning>), String>;
+    let (tx, rx) = mpsc::channel::<(String, BillResult)>();
+    let bill_cli = cli.clone();
+    let bill_config = config.clone();
+    let handles = run_bounded(bill_numbers.clone(), cli.jobs, move |bill_number| {
+        let mut cli = bill_cli.clone();
+        if let Some(output_dir) = &bill_cli.output_dir {
+            cli.output_dir = Some(format!(
+                "{output_dir}/{}",
+                sanitize_folder_name(&bill_number)
+            ));
+        }
+        // Progress lines from several bills would overwrite each other
+        cli.quiet = true;
+        let config = &bill_config;
+        let mut warnings = Vec::new();
+        let result = check_deadline(config, &format!("downloading {bill_number}"))
+            .and_then(|_| {
+                let general_court = cli.general_court.first().map_or("", String::as_str);
+                create_bill_from_bill_number(config, general_court, &bill_number)
+            })
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|(bill, section_counts)| {
+                let failure_count = write_outputs(&cli, config, &bill, &mut warnings)?;
+                Ok((section_counts, failure_count, warnings))
+            })
+            .map_err(|error| error.to_string());
+        tx.send((bill_number, result)).unwrap();
+    });
+    let mut bill_results: Vec<(String, BillResult)> = rx.into_iter().collect();
+    for handle in handles {
+        if handle.join().is_err() {
+            return Err("Bill processing thread panicked".into());
+        }
+    }
+
+    // Summarize the section counts of each bill, in the order given
+    bill_results.sort_by_key(|(bill_number, _)| bill_numbers.iter().position(|b| b == bill_number));
+    let mut bills_section_counts = Vec::new();
+    let mut failure_count = 0;
+    for (bill_number, result) in bill_results {
+        match result {
+            Ok((section_counts, bill_failure_count, bill_warnings)) => {
+                bills_section_counts.push((bill_number, section_counts));
+                failure_count += bill_failure_count;
+                warnings.extend(bill_warnings);
+            }
+            Err(error) if cli.strict => {
+                return Err(format!("Could not process bill {bill_number}: {error}").into());
+            }
+            Err(error) => {
+                eprintln!("Could not process bill {bill_number}: {error}");
+                failure_count += 1;
+            }
+        }
+    }
+    print!("{}", format_section_counts_summary(&bills_section_counts));
+    Ok(failure_count)
+}
 
-            // Create markup documents when output_filename specified
-            if let Some(output_filename) = cli.output_filename {
-                // Download all referenced law sections from bill
-                let law_sections_text = create_law_sections_text(&bill);
+// Without the serde feature, the bill is never printed as JSON
+#[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+fn print_section_counts(
+    cli: &Cli,
+    bill: &Bill,
+    section_counts: SectionCounts,
+) -> Result<(), Box<dyn Error>> {
+    // Print the bill as JSON, when asked, or else its section counts
+    #[cfg(feature = "serde")]
+    if cli.json {
+        return print_bill_json(bill, &section_counts);
+    }
+    print_bill_section_types(section_counts);
+    Ok(())
+}
 
-                // Write the bill text to a file
-                let output_folder = search_term;
-                write_bill(&bill, &output_filename, &output_folder);
+fn write_outputs(
+    cli: &Cli,
+    config: &Config,
+    bill: &Bill,
+    warnings: &mut Vec<Warning>,
+) -> Result<usize, Box<dyn Error>> {
+    // Return the number of law sections which failed to download or mark up
 
-                // Write laws with bill proposed modifications in asciidoc format
-                let law_folder = "modified-laws";
-                write_asciidocs(law_sections_text, &bill, &output_folder, law_folder);
+    // Only the section counts were wanted
+    if cli.counts_only {
+        return Ok(0);
+    }
 
-                // Run asciidoctor over newly created .adoc files
-                run_asciidoctor(output_folder);
-            }
+    // Show how each bill section was parsed, then stop
+    if cli.dump_sections {
+        let section_regex = init_bill_section_regex();
+        print!(
+            "{}",
+            format_bill_sections(&bill.bill_sections, &section_regex)
+        );
+        return Ok(0);
+    }
+
+    // List the law sections to download, then stop
+    if cli.dry_run {
+        print_required_law_sections(&bill.bill_sections);
+        return Ok(0);
+    }
+
+    // Create markup documents when output_filename specified
+    if let Some(output_filename) = &cli.output_filename {
+        let output_folder = &get_output_folder(cli, &bill.bill_number);
+
+        // Download all referenced law sections from bill
+        let (law_sections_text, law_section_failures) = create_law_sections_text(
+            config,
+            &bill.bill_sections,
+            cli.jobs,
+            cli.sequential,
+            init_cache(cli),
+            !cli.quiet && io::stderr().is_terminal(),
+        );
+        for law_section_failure in &law_section_failures {
+            eprintln!("Could not download law section: {law_section_failure}");
+        }
+        // Every download has been tried, so the failures are complete, but no markup is written
+        if cli.strict && !law_section_failures.is_empty() {
+            let law_section_failure = &law_section_failures[0];
+            return Err(format!("Could not download law section: {law_section_failure}").into());
+        }
+
+        // Write the bill text to a file
+        write_bill(&bill.bill_sections, output_filename, output_folder)?;
+
+        // List the files to be written, rendered by asciidoctor too, if it will run
+        let render_html = cli.format == MarkupFormat::Adoc && !cli.no_asciidoctor;
+        let formats = if render_html {
+            vec![cli.format, MarkupFormat::Html]
         } else {
-            info!("Search term is not a bill number")
+            vec![cli.format]
+        };
+        let file_naming = init_file_naming(cli, bill);
+        let mut manifest = create_manifest(
+            config,
+            &law_sections_text,
+            output_folder,
+            &formats,
+            &file_naming,
+            cli.single_doc,
+        );
+
+        // Write asciidoc files to a temporary folder, removed when done, if only HTML is wanted
+        let adoc_temp_dir = if render_html && cli.adoc_temp_dir {
+            Some(tempfile::tempdir()?)
+        } else {
+            None
+        };
+        let adoc_folder = match &adoc_temp_dir {
+            // Keep the name of the output folder, which names the combined document
+            Some(temp_dir) => temp_dir
+                .path()
+                .join(Path::new(output_folder).file_name().unwrap_or_default())
+                .to_string_lossy()
+                .into_owned(),
+            None => output_folder.clone(),
+        };
+
+        // Write laws with bill proposed modifications in asciidoc format
+        let markup_warnings = write_asciidocs(
+            law_sections_text,
+            &bill.bill_sections,
+            &adoc_folder,
+            &file_naming,
+            &init_markup_style(cli),
+            cli.format,
+            cli.single_doc,
+        )?;
+        let markup_failures: Vec<String> = markup_warnings
+            .iter()
+            .filter(|warning| warning.kind == WarningKind::MarkupFailed)
+            .filter_map(|warning| warning.law_chapter_key.clone())
+            .collect();
+        warnings.extend(markup_warnings);
+        // Every law section has been marked up, but none is rendered or indexed
+        if cli.strict && !markup_failures.is_empty() {
+            let markup_failure = &markup_failures[0];
+            return Err(format!("Could not mark up law section: {markup_failure}").into());
+        }
+
+        // Omit law sections which could not be marked up from the files written
+        manifest.retain(|entry| !markup_failures.contains(&entry.law_chapter_key));
+
+        // Run asciidoctor over newly created .adoc files, then keep only the HTML, if asked
+        if render_html {
+            run_asciidoctor(&adoc_folder, &cli.asciidoctor_bin, cli.force)?;
+            if adoc_temp_dir.is_some() {
+                copy_generated_files(
+                    &manifest,
+                    Path::new(&adoc_folder),
+                    output_folder,
+                    MarkupFormat::Html,
+                )?;
+                manifest.retain(|entry| entry.format != MarkupFormat::Adoc.extension());
+            } else if cli.no_keep_adoc {
+                remove_generated_files(&mut manifest, output_folder, MarkupFormat::Adoc)?;
+            }
         }
+
+        // Index the files written
+        write_manifest(&manifest, output_folder)?;
+        return Ok(law_section_failures.len() + markup_failures.len());
     }
+    Ok(0)
 }
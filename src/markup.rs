@@ -1,150 +1,754 @@
-use crate::{bill_section::BillSection, law_section::LawSectionWithText};
-use fancy_regex::Regex;
-use std::{error::Error, path::PathBuf};
+use crate::{
+    bill_section::BillSection,
+    error::SpringbokError,
+    law_section::{log_context, LawSectionWithText},
+};
+use clap::ValueEnum;
+use fancy_regex::{NoExpand, Regex};
+use log::warn;
+use similar::TextDiff;
+use std::{ops::Range, path::PathBuf};
 use walkdir::WalkDir;
 
+#[derive(Debug, Clone)]
+pub struct MarkupStyle {
+    pub strike_role: String,
+    pub insert_role: String,
+}
+
+impl Default for MarkupStyle {
+    fn default() -> Self {
+        MarkupStyle {
+            strike_role: String::from("red"),
+            insert_role: String::from("blue"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MarkupFormat {
+    /// Asciidoc, rendered with asciidoctor
+    #[default]
+    Adoc,
+    /// HTML, with no external renderer needed
+    Html,
+    /// Unified diff of the current and proposed law text
+    Diff,
+}
+
+impl MarkupFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MarkupFormat::Adoc => "adoc",
+            MarkupFormat::Html => "html",
+            MarkupFormat::Diff => "diff",
+        }
+    }
+
+    pub fn renderer(&self, style: &MarkupStyle) -> Box<dyn MarkupRenderer> {
+        match self {
+            MarkupFormat::Adoc => Box::new(AsciidocRenderer {
+                style: style.clone(),
+            }),
+            MarkupFormat::Html => Box::new(HtmlRenderer {
+                style: style.clone(),
+            }),
+            MarkupFormat::Diff => Box::new(PlainRenderer),
+        }
+    }
+}
+
+/// Renders struck and inserted text, footnotes and headings in an output format, so that every
+/// format marks up a law section the same way
+pub trait MarkupRenderer {
+    /// Escape law or bill text so it is not treated as markup
+    fn escape(&self, text: &str) -> String;
+    /// Mark text struck out, paragraph by paragraph
+    fn strike(&self, text: &str) -> String;
+    /// Mark text inserted, paragraph by paragraph
+    fn insert(&self, text: &str) -> String;
+    /// Refer to the bill section which made a change
+    fn footnote(&self, bill_section_number: &str) -> String;
+    /// Add bill section text which could not be applied
+    fn note(&self, bill_section_text: &str) -> String;
+    /// Break a line without starting a new paragraph
+    fn line_break(&self) -> &'static str;
+    /// Head a chapter at level 1, or a section at level 2
+    fn heading(&self, level: usize, title: &str) -> String;
+    /// Wrap marked up law sections as a complete document
+    fn document(&self, title: &str, body: &str) -> String;
+}
+
+pub struct AsciidocRenderer {
+    pub style: MarkupStyle,
+}
+
+impl MarkupRenderer for AsciidocRenderer {
+    fn escape(&self, text: &str) -> String {
+        escape_asciidoc(text)
+    }
+
+    fn strike(&self, text: &str) -> String {
+        let strike_role = &self.style.strike_role;
+        let formatted_text = format_multiline_tags(text, strike_role);
+        format!("[.line-through .{strike_role}]##{formatted_text}##")
+    }
+
+    fn insert(&self, text: &str) -> String {
+        format!("[.{}]##{text}##", self.style.insert_role)
+    }
+
+    fn footnote(&self, bill_section_number: &str) -> String {
+        format!("^{bill_section_number}^")
+    }
+
+    fn note(&self, bill_section_text: &str) -> String {
+        format!("_{bill_section_text}_")
+    }
+
+    fn line_break(&self) -> &'static str {
+        " +\n"
+    }
+
+    fn heading(&self, level: usize, title: &str) -> String {
+        format!("{} {title}", "=".repeat(level + 1))
+    }
+
+    fn document(&self, _title: &str, body: &str) -> String {
+        body.to_string()
+    }
+}
+
+pub struct HtmlRenderer {
+    pub style: MarkupStyle,
+}
+
+impl MarkupRenderer for HtmlRenderer {
+    fn escape(&self, text: &str) -> String {
+        escape_html(text)
+    }
+
+    fn strike(&self, text: &str) -> String {
+        let tag = format!("<del class=\"{}\">", self.style.strike_role);
+        let formatted_text = text.replace("\n\n", &format!("</del>\n\n{tag}"));
+        format!("{tag}{formatted_text}</del>")
+    }
+
+    fn insert(&self, text: &str) -> String {
+        let tag = format!("<ins class=\"{}\">", self.style.insert_role);
+        let formatted_text = text.replace("\n\n", &format!("</ins>\n\n{tag}"));
+        format!("{tag}{formatted_text}</ins>")
+    }
+
+    fn footnote(&self, bill_section_number: &str) -> String {
+        format!("<sup>{bill_section_number}</sup>")
+    }
+
+    fn note(&self, bill_section_text: &str) -> String {
+        format!("<em>{bill_section_text}</em>")
+    }
+
+    fn line_break(&self) -> &'static str {
+        "<br>\n"
+    }
+
+    fn heading(&self, level: usize, title: &str) -> String {
+        format!("<h{0}>{title}</h{0}>", level + 1)
+    }
+
+    fn document(&self, title: &str, body: &str) -> String {
+        // Preserve the blank lines between paragraphs, as asciidoc does
+        let strike_role = &self.style.strike_role;
+        let insert_role = &self.style.insert_role;
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+            main {{ white-space: pre-wrap; }}\n\
+            .{strike_role} {{ color: {strike_role}; }}\n\
+            .{insert_role} {{ color: {insert_role}; }}\n\
+            </style>\n</head>\n<body>\n<main>{body}</main>\n</body>\n</html>\n",
+            escape_html(title)
+        )
+    }
+}
+
+/// Applies struck and inserted text without marking it, giving the proposed law text
+pub struct PlainRenderer;
+
+impl MarkupRenderer for PlainRenderer {
+    fn escape(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn strike(&self, _text: &str) -> String {
+        String::new()
+    }
+
+    fn insert(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn footnote(&self, _bill_section_number: &str) -> String {
+        String::new()
+    }
+
+    fn note(&self, _bill_section_text: &str) -> String {
+        String::new()
+    }
+
+    fn line_break(&self) -> &'static str {
+        "\n"
+    }
+
+    fn heading(&self, _level: usize, title: &str) -> String {
+        title.to_string()
+    }
+
+    fn document(&self, _title: &str, body: &str) -> String {
+        body.to_string()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MarkupRegex {
+    pub style: MarkupStyle,
+    pub format: MarkupFormat,
     text_parse: Regex,
     striking: Regex,
     inserting: Regex,
+    adding: Regex,
     words: Regex,
+    figures: Regex,
     sections: Regex,
     subsections: Regex,
     lines: Regex,
     repealed: Regex,
+    repeal_and_replace: Regex,
     replace_words: Regex,
+    replace_figures: Regex,
     replace_lines: Regex,
     replace_section: Regex,
     replace_subsection: Regex,
     strike_words: Regex,
+    strike_figures: Regex,
     strike_lines: Regex,
     strike_section: Regex,
-    insert_words: Regex,
+    strike_subsection: Regex,
     insert_lines: Regex,
     insert_section: Regex,
     match_sections: Regex,
+    inserted_section: Regex,
+    occurrence: Regex,
+    every_occurrence: Regex,
+    in_line: Regex,
+    in_subsection: Regex,
+    insert_position: Regex,
+    nested_subsection: Regex,
+    subsection_label: Regex,
+    label_start: Regex,
+    insert_following: Regex,
+    punctuation: PunctuationRegex,
+}
+
+#[derive(Debug, Clone)]
+pub struct PunctuationRegex {
+    colon_dash: Regex,
+    sections: Regex,
+    section: Regex,
+}
+
+pub fn init_punctuation_regex() -> PunctuationRegex {
+    PunctuationRegex {
+        colon_dash: Regex::new(r":[ \t]*[-\u{2012}\u{2013}\u{2014}\u{2015}]+").unwrap(),
+        sections: Regex::new(r"§§[ \t]*").unwrap(),
+        section: Regex::new(r"§[ \t]*").unwrap(),
+    }
+}
+
+// Labels of nested subsections, clauses, and so on, outermost first, such as ["b", "iii"] for
+// "clause (iii) of subsection (b)"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubsectionChain {
+    pub labels: Vec<String>,
 }
 
 pub struct MarkedLawSection {
-    pub(crate) chapter_number: String,
-    pub(crate) section_number: String,
-    pub(crate) text: String,
+    pub chapter_number: String,
+    pub section_number: String,
+    pub text: String,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WarningKind {
+    NotFound,
+    Ambiguous,
+    LineReference,
+    Unrecognized,
+    MarkupFailed,
+}
+
+impl WarningKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            WarningKind::NotFound => "bill section(s) footnoted as the text to amend was not found",
+            WarningKind::Ambiguous => {
+                "bill section(s) footnoted as the text to amend appears more than once"
+            }
+            WarningKind::LineReference => "bill section(s) footnoted due to line references",
+            WarningKind::Unrecognized => {
+                "bill section(s) left unmarked as the amendment was not recognized"
+            }
+            WarningKind::MarkupFailed => "law section(s) could not be marked up",
+        }
+    }
+}
+
+// A problem met while marking up, kept so the run can end with a summary of them all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub bill_section_number: Option<String>,
+    pub law_chapter_key: Option<String>,
+}
+
+impl Warning {
+    fn new(kind: WarningKind, bill_section_number: &str, law_chapter_key: Option<&str>) -> Self {
+        Warning {
+            kind,
+            bill_section_number: Some(bill_section_number.to_string()),
+            law_chapter_key: law_chapter_key.map(String::from),
+        }
+    }
+}
+
+pub fn format_warning_summary(warnings: &[Warning]) -> String {
+    // Group warnings by kind, with a count and the sections affected by each
+    let mut kinds: Vec<WarningKind> = warnings.iter().map(|warning| warning.kind).collect();
+    kinds.sort();
+    kinds.dedup();
+    let mut summary = String::new();
+    for kind in kinds {
+        let sections: Vec<String> = warnings
+            .iter()
+            .filter(|warning| warning.kind == kind)
+            .map(|warning| {
+                let context = log_context(
+                    warning.bill_section_number.as_deref(),
+                    warning.law_chapter_key.as_deref(),
+                );
+                context[1..context.len() - 1].to_string()
+            })
+            .collect();
+        summary.push_str(&format!(
+            "{} {}: {}\n",
+            sections.len(),
+            kind.description(),
+            sections.join(", ")
+        ));
+    }
+    summary
 }
 
 // TODO: Document these?
 pub fn init_markup_regex() -> MarkupRegex {
     MarkupRegex {
+        style: MarkupStyle::default(),
+        format: MarkupFormat::default(),
         text_parse: Regex::new(r"((?i)section.*)[\n\s]*([\S\s]*)").unwrap(),
         striking: Regex::new(r"strik").unwrap(),
         inserting: Regex::new(r"insert").unwrap(),
+        adding: Regex::new(r"\badding the following\b").unwrap(),
         words: Regex::new(r"words?").unwrap(),
+        figures: Regex::new(r"\bfigures?\b").unwrap(),
         sections: Regex::new(r"sections?:").unwrap(),
         subsections: Regex::new(r"(subsections?|subclauses?):").unwrap(),
         lines: Regex::new(r"^.*SECTION.*lines?").unwrap(),
         repealed: Regex::new(r"repealed ?(.*)").unwrap(),
+        repeal_and_replace: Regex::new(
+            r"repealed,? and the following (?:\w+ )*?(?:inserted|substituted)[^:]*:-?([\s\S]*)",
+        )
+        .unwrap(),
         replace_words: Regex::new(r#"strik.*(“|")(.*)(”|").*insert.*?:-? (.*)\."#).unwrap(),
-        replace_lines: Regex::new(r#"strik.*lines (\d*)[^\d]*(\d*).*insert.*?:-?(.*)"#).unwrap(),
+        replace_figures: Regex::new(
+            r#"strik.*?figures? "(.*?)".*?insert.*?figures?(?: "(.*?)"|:-?\s*(.*?)\.?\s*$)"#,
+        )
+        .unwrap(),
+        replace_lines: Regex::new(
+            r"strik.*lines? (\d+)(?:\s*(?:to|through|and|-)\s*(\d+))?.*insert.*?:-?\s*([\s\S]*)",
+        )
+        .unwrap(),
         replace_section: Regex::new(r"strik?.*section.*insert.*?:-?([\s\S]*)").unwrap(),
         replace_subsection: Regex::new(
-            r"strik?.*(subsection|subclause) \((.)\).*insert.*?:-?([\s\S]*)",
+            r"strik?.*(subsection|subclause) \(([0-9a-zA-Z]+)\).*insert.*?:-?([\s\S]*)",
         )
         .unwrap(),
-        strike_words: Regex::new(r#"strik.*(“|")(.*)(”|")?\."#).unwrap(),
-        strike_lines: Regex::new(r"strike_lines").unwrap(), //TODO: Implement
-        strike_section: Regex::new(r"strike_section").unwrap(), //TODO: Implement
-        insert_words: Regex::new(r#"insert.*word.*(“|")(.*)(”|").*.*?:-? (.*)\."#).unwrap(),
-        insert_lines: Regex::new(r"insert_lines").unwrap(), //TODO: Implement
-        insert_section: Regex::new(r"insert.*sections?:-?([\s\S]*)").unwrap(),
+        strike_words: Regex::new(r#"strik.*?(“|")(.*?)(”|")"#).unwrap(),
+        strike_figures: Regex::new(r#"strik.*?figures? "(.*?)""#).unwrap(),
+        strike_lines: Regex::new(r"strik.*lines? (\d+)(?:\s*(?:to|through|and|-)\s*(\d+))?")
+            .unwrap(),
+        strike_section: Regex::new(r"strik.*\bsections?\b").unwrap(),
+        strike_subsection: Regex::new(r"strik.*(subsection|subclause) \(([0-9a-zA-Z]+)\)")
+            .unwrap(),
+        insert_lines: Regex::new(r"insert.*lines? (\d+).*?:-?\s*([\s\S]*)").unwrap(),
+        insert_section: Regex::new(r"(?:insert|\badding\b).*sections?:-?([\s\S]*)").unwrap(),
+        nested_subsection: Regex::new(
+            r"(?i)strik\w*(?: out)? ((?:(?:sub)?(?:section|paragraph|clause) \([0-9a-zA-Z]+\),? of (?:the )?)+(?:sub)?(?:section|paragraph|clause) \([0-9a-zA-Z]+\))",
+        )
+        .unwrap(),
+        subsection_label: Regex::new(r"\(([0-9a-zA-Z]+)\)").unwrap(),
+        // Labels begin a line, possibly after the section number, or follow a colon or semicolon
+        label_start: Regex::new(r"(?im)(?:^(?:section \d+\w*\.\s*)?|[:;]\s*)\(([0-9a-z]+)\)")
+            .unwrap(),
+        insert_following: Regex::new(r"insert.*?:-?\s*([\s\S]*)").unwrap(),
         match_sections: Regex::new(r"Section[\s\S]*?(?=Section|\z)").unwrap(),
+        inserted_section: Regex::new(
+            r"^Section\s+(\d+\w*(?:[\u00BC-\u00BE\u2150-\u215E]\w*)?)\.?\s*([\s\S]*)",
+        )
+        .unwrap(),
+        occurrence: Regex::new(
+            r"(first|second|third|fourth|fifth|sixth|seventh|eighth|ninth|tenth) time (?:it|they) appears?",
+        )
+        .unwrap(),
+        every_occurrence: Regex::new(
+            r"\b(?:wherever|each time|every time) (?:it|they) appears?|\bwherever appearing",
+        )
+        .unwrap(),
+        in_line: Regex::new(r"\bin line (\d+)").unwrap(),
+        in_subsection: Regex::new(r"\b(?:subsection|clause) \((\w+)\)").unwrap(),
+        insert_position: Regex::new(
+            r"^[^:]*?\b((after|before) section \d+[\u00BC-\u00BE\u2150-\u215E]?[A-Z]*|at the end(?: of (?:chapter|section) \d+[A-Z]*| thereof)?)",
+        )
+        .unwrap(),
+        punctuation: init_punctuation_regex(),
     }
 }
-pub(crate) fn mark_section_text(
+pub fn mark_section_text(
     law_section: &LawSectionWithText,
-    bill_sections: &Vec<BillSection>,
+    bill_sections: &[BillSection],
     markup_regex: &MarkupRegex,
 ) -> Option<MarkedLawSection> {
+    let renderer = markup_regex.format.renderer(&markup_regex.style);
+
     // Parse law section title and contents
     if let Ok(Some(caps)) = markup_regex.text_parse.captures(law_section.text.as_ref()) {
-        let title = String::from(caps[1].trim());
-        let law_section_text = String::from(caps[2].trim());
-
-        let mut marked_text = law_section_text.clone();
+        // Escape law and bill text, so only the markup added below is treated as markup
+        let title = renderer.escape(caps[1].trim());
+        let law_section_text = renderer.escape(caps[2].trim());
+        let line_index: Option<Vec<String>> = law_section
+            .line_index
+            .as_ref()
+            .map(|law_lines| law_lines.iter().map(|l| renderer.escape(l)).collect());
 
         // Apply markups for law_section across all applicable bill sections
-        for bill_section_key in &law_section.bill_section_keys {
-            if let Some(bill_section) = bill_sections
-                .iter()
-                .find(|bill_section| &bill_section.section_number == bill_section_key)
-            {
-                marked_text = mark_text(
-                    &marked_text,
-                    &bill_section.text,
-                    &bill_section.section_number,
-                    markup_regex,
-                );
-            }
-        }
+        let mut warnings = Vec::new();
+        let marked_text = mark_bill_sections(
+            &law_section_text,
+            law_section,
+            bill_sections,
+            line_index.as_deref(),
+            renderer.as_ref(),
+            markup_regex,
+            &mut warnings,
+        );
 
         let mut key_split = law_section.law_chapter_key.split("-");
         let law_chapter_number = key_split.next().unwrap();
         let law_section_number = key_split.next().unwrap();
-        let marked_section_text = format!("=== {title}\n\n{marked_text}");
+        let marked_section_text = match markup_regex.format {
+            MarkupFormat::Diff => get_unified_diff(
+                &law_section.law_chapter_key,
+                &format!("{title}\n\n{law_section_text}"),
+                &format!("{title}\n\n{marked_text}"),
+            ),
+            _ => format!("{}\n\n{marked_text}", renderer.heading(2, &title)),
+        };
         let marked_law_section = MarkedLawSection {
             chapter_number: law_chapter_number.to_string(),
             section_number: law_section_number.to_string(),
             text: marked_section_text,
+            warnings,
         };
         return Some(marked_law_section);
     }
     None
 }
 
-fn mark_text(
-    law_section_text: &String,
-    bill_section_text: &String,
-    bill_section_number: &String,
+fn mark_bill_sections(
+    law_section_text: &str,
+    law_section: &LawSectionWithText,
+    bill_sections: &[BillSection],
+    line_index: Option<&[String]>,
+    renderer: &dyn MarkupRenderer,
+    markup_regex: &MarkupRegex,
+    warnings: &mut Vec<Warning>,
+) -> String {
+    let mut marked_text = law_section_text.to_string();
+    for bill_section_key in &law_section.bill_section_keys {
+        if let Some(bill_section) = bill_sections
+            .iter()
+            .find(|bill_section| &bill_section.section_number == bill_section_key)
+        {
+            // Resolve line references against the bill's own line numbers, if it has them
+            let bill_line_index: Option<Vec<String>> =
+                bill_section.line_refs.as_ref().map(|line_refs| {
+                    line_refs
+                        .line_index()
+                        .iter()
+                        .map(|line| renderer.escape(line))
+                        .collect()
+                });
+            marked_text = mark_law_text(
+                &marked_text,
+                &renderer.escape(&bill_section.text),
+                &bill_section.section_number,
+                Some(&law_section.law_chapter_key),
+                bill_line_index.as_deref().or(line_index),
+                markup_regex,
+                warnings,
+            );
+        }
+    }
+    marked_text
+}
+
+pub fn apply_amendments(
+    law_section: &LawSectionWithText,
+    bill_sections: &[BillSection],
     markup_regex: &MarkupRegex,
 ) -> String {
+    // Apply all applicable bill sections without markup, leaving the law text as amended
+    let mut plain_regex = markup_regex.clone();
+    plain_regex.format = MarkupFormat::Diff;
+    let (title, law_section_text) = match plain_regex.text_parse.captures(&law_section.text) {
+        Ok(Some(caps)) => (caps[1].trim().to_string(), caps[2].trim().to_string()),
+        _ => (String::new(), law_section.text.trim().to_string()),
+    };
+    let amended_text = mark_bill_sections(
+        &law_section_text,
+        law_section,
+        bill_sections,
+        law_section.line_index.as_deref(),
+        &PlainRenderer,
+        &plain_regex,
+        &mut Vec::new(),
+    );
+    normalize_plain_text(&format!("{title}\n\n{amended_text}"))
+}
+
+pub fn diff_section_text(
+    law_section: &LawSectionWithText,
+    bill_sections: &[BillSection],
+    markup_regex: &MarkupRegex,
+) -> Option<String> {
+    // Apply all applicable bill sections without markup, then diff against the current law
+    let mut diff_regex = markup_regex.clone();
+    diff_regex.format = MarkupFormat::Diff;
+    mark_section_text(law_section, bill_sections, &diff_regex)
+        .map(|marked_law_section| marked_law_section.text)
+}
+
+fn get_unified_diff(law_chapter_key: &str, old_text: &str, new_text: &str) -> String {
+    // Normalize both texts so unchanged lines still match
+    let old_text = format!("{}\n", normalize_plain_text(old_text));
+    let new_text = format!("{}\n", normalize_plain_text(new_text));
+    TextDiff::from_lines(&old_text, &new_text)
+        .unified_diff()
+        .header(
+            &format!("a/{law_chapter_key}"),
+            &format!("b/{law_chapter_key}"),
+        )
+        .to_string()
+}
+
+fn normalize_plain_text(text: &str) -> String {
+    // Normalize spacing left behind where text was struck
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !(line.is_empty() && lines.last().is_none_or(|last| last.is_empty())) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim_end().to_string()
+}
+
+pub fn mark_text(
+    law_section_text: &str,
+    bill_section_text: &str,
+    bill_section_number: &str,
+    line_index: Option<&[String]>,
+    markup_regex: &MarkupRegex,
+) -> String {
+    mark_law_text(
+        law_section_text,
+        bill_section_text,
+        bill_section_number,
+        None,
+        line_index,
+        markup_regex,
+        &mut Vec::new(),
+    )
+}
+
+fn mark_law_text(
+    law_section_text: &str,
+    bill_section_text: &str,
+    bill_section_number: &str,
+    law_chapter_key: Option<&str>,
+    line_index: Option<&[String]>,
+    markup_regex: &MarkupRegex,
+    warnings: &mut Vec<Warning>,
+) -> String {
+    // Prefix each message with the bill section, and law section when known
+    let context = log_context(Some(bill_section_number), law_chapter_key);
+
+    // Unify quotes, spaces, and punctuation, so a difference in style does not prevent a match
+    let punctuation_regex = &markup_regex.punctuation;
+    let law_section_text =
+        &normalize_punctuation(&normalize_quotes(law_section_text), punctuation_regex);
+    let bill_section_text =
+        &normalize_punctuation(&normalize_quotes(bill_section_text), punctuation_regex);
+
     // Section amends an existing law
     let is_repealing = markup_regex.repealed.is_match(bill_section_text).unwrap();
     let is_striking = markup_regex.striking.is_match(bill_section_text).unwrap();
-    let is_inserting = markup_regex
-        .inserting
-        .is_match(&*bill_section_text)
-        .unwrap();
+    // Such as "amended by adding the following section", which appends to the chapter
+    let is_adding = markup_regex.adding.is_match(bill_section_text).unwrap();
+    let is_inserting = markup_regex.inserting.is_match(bill_section_text).unwrap() || is_adding;
     let is_words = markup_regex.words.is_match(bill_section_text).unwrap();
+    let is_figures = markup_regex.figures.is_match(bill_section_text).unwrap();
     let is_sections = markup_regex.sections.is_match(bill_section_text).unwrap();
+    let is_striking_section = markup_regex
+        .strike_section
+        .is_match(bill_section_text)
+        .unwrap();
     let is_subsections = markup_regex
         .subsections
-        .is_match(&*bill_section_text)
+        .is_match(bill_section_text)
         .unwrap();
     let is_lines = markup_regex.lines.is_match(bill_section_text).unwrap();
+    // Such as "wherever it appears", so each occurrence is amended, not just one
+    let is_every_occurrence = markup_regex
+        .every_occurrence
+        .is_match(bill_section_text)
+        .unwrap();
+    // Such as "clause (iii) of subsection (b)", located by descending through each label
+    let nested_subsection =
+        parse_subsection_chain(bill_section_text, markup_regex).and_then(|subsection_chain| {
+            find_nested_subsection(law_section_text, &subsection_chain, markup_regex)
+        });
     let mut marked_text = law_section_text.clone();
+    let renderer = markup_regex.format.renderer(&markup_regex.style);
+    let footnote = renderer.footnote(bill_section_number);
 
     // Repealing
     if is_repealing {
-        if let Ok(Some(caps)) = markup_regex.repealed.captures(bill_section_text.as_ref()) {
+        // Such as "repealed and the following section inserted in place thereof:-", which
+        // replaces the section
+        if let Ok(Some(caps)) = markup_regex
+            .repeal_and_replace
+            .captures(bill_section_text.as_ref())
+        {
+            let insert = renderer.insert(caps[1].trim());
+            if law_section_text.is_empty() {
+                marked_text = format!("{insert}{footnote}")
+            } else {
+                marked_text = format!(
+                    "{}\n\n{insert}{footnote}",
+                    renderer.strike(law_section_text)
+                )
+            }
+        } else if let Ok(Some(caps)) = markup_regex.repealed.captures(bill_section_text.as_ref()) {
             let repeal_specifications = String::from(&caps[1]);
 
             // law_section_text may be empty if the repeal has already gone through
             if law_section_text.is_empty() {
-                marked_text = format!("REPEALED {repeal_specifications}^{bill_section_number}^")
+                marked_text = format!("REPEALED {repeal_specifications}{footnote}")
             } else {
-                let formatted_law_section_text = format_multiline_tags(law_section_text);
-
                 marked_text = format!(
-                    "\
-            [.line-through .red]##{formatted_law_section_text}##^{bill_section_number}^\n\nREPEALED {repeal_specifications}
-            "
+                    "{}{footnote}\n\nREPEALED {repeal_specifications}",
+                    renderer.strike(law_section_text)
                 )
             }
         }
     }
     // Striking and Inserting
     else if is_striking && is_inserting {
+        // Striking and inserting a nested subsection, clause, and so on
+        if let Some(range) = nested_subsection {
+            let insert = markup_regex
+                .insert_following
+                .captures(bill_section_text)
+                .ok()
+                .flatten()
+                .map_or(String::new(), |caps| String::from(caps[1].trim()));
+
+            // Format replacement
+            let mut replacement = format!(
+                "{}\n\n{}{footnote}",
+                renderer.strike(&law_section_text[range.clone()]),
+                renderer.insert(&insert)
+            );
+            replacement = replacement.replace("\n", renderer.line_break());
+
+            marked_text = format!(
+                "{}{replacement}{}",
+                &law_section_text[..range.start],
+                &law_section_text[range.end..]
+            )
+        }
+        // Striking and inserting figures
+        else if is_figures {
+            if let Ok(Some(caps)) = markup_regex
+                .replace_figures
+                .captures(bill_section_text.as_ref())
+            {
+                let striked_figures = String::from(&caps[1]);
+                let inserted_figures = caps
+                    .get(2)
+                    .or(caps.get(3))
+                    .map_or(String::new(), |m| String::from(m.as_str()));
+
+                // Replace the figure only where it is not part of a longer number
+                let occurrence = find_occurrence(
+                    law_section_text,
+                    &striked_figures,
+                    true,
+                    bill_section_text,
+                    line_index,
+                    markup_regex,
+                );
+                if let Some(start) = occurrence {
+                    // Format replacement
+                    let replacement = format!(
+                        "{} {}{footnote}",
+                        renderer.strike(&striked_figures),
+                        renderer.insert(&inserted_figures)
+                    );
+
+                    let end = start + striked_figures.len();
+                    marked_text = format!(
+                        "{}{replacement}{}",
+                        &law_section_text[..start],
+                        &law_section_text[end..]
+                    )
+                } else {
+                    warn!("{context} replacing figures: could not find \"{striked_figures}\", or it is ambiguous, footnoting");
+                    warnings.push(Warning::new(
+                        WarningKind::NotFound,
+                        bill_section_number,
+                        law_chapter_key,
+                    ));
+                    marked_text = format!(
+                        "{}\n\n{}",
+                        law_section_text,
+                        renderer.note(bill_section_text.trim())
+                    )
+                }
+            }
+        }
         // Striking and inserting words
-        if is_words {
+        else if is_words {
             if let Ok(Some(caps)) = markup_regex
                 .replace_words
                 .captures(bill_section_text.as_ref())
@@ -153,55 +757,105 @@ fn mark_text(
                 let inserted_words = String::from(&caps[4]);
                 let mut buffer = "";
 
-                let matches: Vec<&str> = law_section_text.matches(&striked_words).collect();
-                // Replace word(s) only if one instance appears
-                if matches.len() == 1 {
-                    // Handle asciidoc not marking up document if buffer before class not present
-                    if striked_words.starts_with([',', '.', ':', ' ']) {
-                        buffer = " ";
-                    }
-                    // Format replacement
-                    let replacement = format!(
-                        "\
-                    {buffer}[.line-through .red]##{striked_words}## \
-                    [.blue]##{inserted_words}##^{bill_section_number}^\
-                    "
+                // Replace word(s) only if the instance to replace can be found
+                let occurrence = find_occurrence(
+                    law_section_text,
+                    &striked_words,
+                    false,
+                    bill_section_text,
+                    line_index,
+                    markup_regex,
+                );
+                // Handle asciidoc not marking up document if buffer before class not present
+                if striked_words.starts_with([',', '.', ':', ' ']) {
+                    buffer = " ";
+                }
+                // Format replacement
+                let replacement = format!(
+                    "{buffer}{} {}{footnote}",
+                    renderer.strike(&striked_words),
+                    renderer.insert(&inserted_words)
+                );
+                let words_regex = get_words_regex(&striked_words);
+                if is_every_occurrence && words_regex.is_match(law_section_text).unwrap() {
+                    marked_text = words_regex
+                        .replace_all(law_section_text, NoExpand(&replacement))
+                        .into_owned()
+                } else if let Some(start) = occurrence {
+                    let end = start + striked_words.len();
+                    marked_text = format!(
+                        "{}{replacement}{}",
+                        &law_section_text[..start],
+                        &law_section_text[end..]
+                    )
+                } else if !law_section_text.contains(&striked_words) {
+                    warn!(
+                        "{context} replacing words: could not find \"{striked_words}\", footnoting"
                     );
-
-                    marked_text = law_section_text.replace(&striked_words, &*replacement)
+                    warnings.push(Warning::new(
+                        WarningKind::NotFound,
+                        bill_section_number,
+                        law_chapter_key,
+                    ));
+                    marked_text = format!(
+                        "{}\n\n{}",
+                        law_section_text,
+                        renderer.note(bill_section_text.trim())
+                    )
                 } else {
-                    println!(
-                        "Replacing Words: ambiguous - bill section will be added as a footnote."
-                    );
-                    marked_text = format!("{}\n\n_{}_", law_section_text, bill_section_text.trim())
+                    warn!("{context} ambiguous replacement of \"{striked_words}\", footnoting");
+                    warnings.push(Warning::new(
+                        WarningKind::Ambiguous,
+                        bill_section_number,
+                        law_chapter_key,
+                    ));
+                    marked_text = format!(
+                        "{}\n\n{}",
+                        law_section_text,
+                        renderer.note(bill_section_text.trim())
+                    )
                 }
             }
         }
         // Striking and inserting line(s)
         else if is_lines {
-            // if let Ok(Some(caps)) = markup_regex
-            //     .replace_lines
-            //     .captures(bill_section_text.as_ref())
-            // {
-            //     let strike_start_line = String::from(&caps[1]);
-            //     let strike_end_line = String::from(&caps[2]);
-            //     let inserted_words = String::from(&caps[3]);
-            //
-            //     //TODO: figure out how to convert line numbers into actual strings
-            //     let striked_words = String::from("PLACEHOLDER");
-            //
-            //     // Format replacement
-            //     let replacement = format!(
-            //         "\
-            //     [.line-through .red]##{striked_words}## \
-            //     [.blue]##{inserted_words}##^{bill_section_number}^\
-            //     "
-            //     );
-            //
-            //     marked_text = law_section_text.replace(&striked_words, &*replacement)
-            // }
-            println!("Replacing Line: line numbers are not included in the online version of the law, and thus cannot be accurately included. Bill section will be added as a footnote.");
-            marked_text = format!("{}\n\n_{}_", law_section_text, bill_section_text.trim())
+            let replaced_lines = markup_regex
+                .replace_lines
+                .captures(bill_section_text.as_ref())
+                .ok()
+                .flatten()
+                .and_then(|caps| {
+                    let striked_lines = get_lines(
+                        line_index?,
+                        &caps[1],
+                        caps.get(2).map(|m| m.as_str()),
+                        &markup_regex.punctuation,
+                    )?;
+                    Some((striked_lines, String::from(caps[3].trim())))
+                })
+                .filter(|(striked_lines, _)| law_section_text.contains(striked_lines));
+            if let Some((striked_lines, inserted_words)) = replaced_lines {
+                // Format replacement
+                let replacement = format!(
+                    "{} {}{footnote}",
+                    renderer.strike(&striked_lines),
+                    renderer.insert(&inserted_words)
+                );
+
+                marked_text = law_section_text.replacen(&striked_lines, &replacement, 1)
+            } else {
+                warn!("{context} replacing lines: the online law has no line numbers, footnoting");
+                warnings.push(Warning::new(
+                    WarningKind::LineReference,
+                    bill_section_number,
+                    law_chapter_key,
+                ));
+                marked_text = format!(
+                    "{}\n\n{}",
+                    law_section_text,
+                    renderer.note(bill_section_text.trim())
+                )
+            }
         }
         // Striking and inserting subsections(s)
         else if is_subsections {
@@ -209,31 +863,24 @@ fn mark_text(
                 .replace_subsection
                 .captures(bill_section_text.as_ref())
             {
-                let subsection_char = String::from(caps[2].trim());
+                let subsection_label = String::from(caps[2].trim());
                 let insert = String::from(caps[3].trim());
 
-                // Create string for regex
-                let get_subsection_regex_string = format!(
-                    r"(?i)(\n|^)(section \d+.\s*)?(\({}\))([\s\S]*?)\n(\[.*\]|\([^\d\W]\))",
-                    subsection_char
-                );
-                let get_subsection_regex =
-                    Regex::new(get_subsection_regex_string.as_ref()).unwrap();
-                if let Ok(Some(caps)) = get_subsection_regex.captures(law_section_text.as_ref()) {
+                let subsection_regex = get_subsection_regex(&subsection_label);
+                if let Ok(Some(caps)) = subsection_regex.captures(law_section_text.as_ref()) {
                     let subsection_header = String::from(caps[3].trim());
                     let subsection_content = String::from(caps[4].trim());
                     let subsection = format!("{} {}", subsection_header, subsection_content);
-                    let formatted_subsection = format_multiline_tags(&subsection);
 
                     // Format replacement
                     let mut replacement = format!(
-                        "\
-                [.line-through .red]##{formatted_subsection}##\n\n[.blue]##{insert}##^{bill_section_number}^\
-                "
+                        "{}\n\n{}{footnote}",
+                        renderer.strike(&subsection),
+                        renderer.insert(&insert)
                     );
-                    replacement = replacement.replace("\n", " +\n");
+                    replacement = replacement.replace("\n", renderer.line_break());
 
-                    marked_text = law_section_text.replace(&subsection, &*replacement)
+                    marked_text = law_section_text.replace(&subsection, &replacement)
                 }
             }
         }
@@ -244,56 +891,230 @@ fn mark_text(
                 .captures(bill_section_text.as_ref())
             {
                 let insert = String::from(caps[1].trim());
-                let formatted_law_section_text = format_multiline_tags(law_section_text);
                 // Format replacement
                 marked_text = format!(
-                    "\
-                [.line-through .red]##{formatted_law_section_text}##\n\n[.blue]##{insert}##^{bill_section_number}^\
-                "
+                    "{}\n\n{}{footnote}",
+                    renderer.strike(law_section_text),
+                    renderer.insert(&insert)
                 )
             }
         }
     }
     // Striking
     else if is_striking {
+        // Striking a nested subsection, clause, and so on
+        if let Some(range) = nested_subsection {
+            marked_text = format!(
+                "{}{}{footnote}{}",
+                &law_section_text[..range.start],
+                renderer.strike(&law_section_text[range.clone()]),
+                &law_section_text[range.end..]
+            )
+        }
+        // Striking figures
+        else if is_figures {
+            if let Ok(Some(caps)) = markup_regex
+                .strike_figures
+                .captures(bill_section_text.as_ref())
+            {
+                let striked_figures = String::from(&caps[1]);
+                let occurrence = find_occurrence(
+                    law_section_text,
+                    &striked_figures,
+                    true,
+                    bill_section_text,
+                    line_index,
+                    markup_regex,
+                );
+                if let Some(start) = occurrence {
+                    // Format replacement
+                    let replacement = format!("{}{footnote}", renderer.strike(&striked_figures));
+
+                    let end = start + striked_figures.len();
+                    marked_text = format!(
+                        "{}{replacement}{}",
+                        &law_section_text[..start],
+                        &law_section_text[end..]
+                    )
+                } else {
+                    warn!("{context} striking figures: could not find \"{striked_figures}\", or it is ambiguous, footnoting");
+                    warnings.push(Warning::new(
+                        WarningKind::NotFound,
+                        bill_section_number,
+                        law_chapter_key,
+                    ));
+                    marked_text = format!(
+                        "{}\n\n{}",
+                        law_section_text,
+                        renderer.note(bill_section_text.trim())
+                    )
+                }
+            }
+        }
         // Striking words
-        if is_words {
+        else if is_words {
             if let Ok(Some(caps)) = markup_regex
                 .strike_words
                 .captures(bill_section_text.as_ref())
             {
                 let striked_words = String::from(&caps[2]);
-                // Format replacement
-                let replacement = format!(
-                    "\
-                    [.line-through .red]##{striked_words}##^{bill_section_number}^ \
-                    "
+                let occurrence = find_occurrence(
+                    law_section_text,
+                    &striked_words,
+                    false,
+                    bill_section_text,
+                    line_index,
+                    markup_regex,
                 );
-
-                marked_text = law_section_text.replace(&striked_words, &*replacement)
+                // Format replacement
+                let replacement = format!("{}{footnote} ", renderer.strike(&striked_words));
+                let words_regex = get_words_regex(&striked_words);
+                if is_every_occurrence && words_regex.is_match(law_section_text).unwrap() {
+                    marked_text = words_regex
+                        .replace_all(law_section_text, NoExpand(&replacement))
+                        .into_owned()
+                } else if let Some(start) = occurrence {
+                    let end = start + striked_words.len();
+                    marked_text = format!(
+                        "{}{replacement}{}",
+                        &law_section_text[..start],
+                        &law_section_text[end..]
+                    )
+                } else if !law_section_text.contains(&striked_words) {
+                    warn!(
+                        "{context} striking words: could not find \"{striked_words}\", footnoting"
+                    );
+                    warnings.push(Warning::new(
+                        WarningKind::NotFound,
+                        bill_section_number,
+                        law_chapter_key,
+                    ));
+                    marked_text = format!(
+                        "{}\n\n{}",
+                        law_section_text,
+                        renderer.note(bill_section_text.trim())
+                    )
+                } else {
+                    warn!("{context} ambiguous striking of \"{striked_words}\", footnoting");
+                    warnings.push(Warning::new(
+                        WarningKind::Ambiguous,
+                        bill_section_number,
+                        law_chapter_key,
+                    ));
+                    marked_text = format!(
+                        "{}\n\n{}",
+                        law_section_text,
+                        renderer.note(bill_section_text.trim())
+                    )
+                }
             }
         }
         // Striking line(s)
         else if is_lines {
-            println!("Striking Line: line numbers are not included in the online version of the law, and thus cannot be accurately included. Bill section will be added as a footnote.");
-            marked_text = format!("{}\n\n_{}_", law_section_text, bill_section_text.trim())
+            let striked_lines = markup_regex
+                .strike_lines
+                .captures(bill_section_text.as_ref())
+                .ok()
+                .flatten()
+                .and_then(|caps| {
+                    get_lines(
+                        line_index?,
+                        &caps[1],
+                        caps.get(2).map(|m| m.as_str()),
+                        &markup_regex.punctuation,
+                    )
+                })
+                .filter(|striked_lines| law_section_text.contains(striked_lines));
+            if let Some(striked_lines) = striked_lines {
+                // Format replacement
+                let replacement = format!("{}{footnote}", renderer.strike(&striked_lines));
+
+                marked_text = law_section_text.replacen(&striked_lines, &replacement, 1)
+            } else {
+                warn!("{context} striking lines: the online law has no line numbers, footnoting");
+                warnings.push(Warning::new(
+                    WarningKind::LineReference,
+                    bill_section_number,
+                    law_chapter_key,
+                ));
+                marked_text = format!(
+                    "{}\n\n{}",
+                    law_section_text,
+                    renderer.note(bill_section_text.trim())
+                )
+            }
+        }
+        // Striking subsection
+        else if let Ok(Some(caps)) = markup_regex
+            .strike_subsection
+            .captures(bill_section_text.as_ref())
+        {
+            let subsection_regex = get_subsection_regex(caps[2].trim());
+            if let Ok(Some(caps)) = subsection_regex.captures(law_section_text.as_ref()) {
+                let subsection_header = String::from(caps[3].trim());
+                let subsection_content = String::from(caps[4].trim());
+                let subsection = format!("{} {}", subsection_header, subsection_content);
+                // Format replacement
+                let replacement = format!("{}{footnote}", renderer.strike(&subsection));
+
+                marked_text = law_section_text.replacen(&subsection, &replacement, 1)
+            }
         }
         // Striking section(s)
-        else if is_sections {
-            println!("Striking sections not implemented!")
+        else if is_sections || is_striking_section {
+            marked_text = format!("{}{footnote}", renderer.strike(law_section_text))
         }
     }
     // Inserting
     else if is_inserting {
         // Inserting words
         if is_words {
-            println!("Inserting Words (at line): line numbers are not included in the online version of the law, and thus cannot be accurately included. Bill section will be added as a footnote.");
-            marked_text = format!("{}\n\n_{}_", law_section_text, bill_section_text.trim())
+            warn!("{context} inserting words: the online law has no line numbers, footnoting");
+            warnings.push(Warning::new(
+                WarningKind::LineReference,
+                bill_section_number,
+                law_chapter_key,
+            ));
+            marked_text = format!(
+                "{}\n\n{}",
+                law_section_text,
+                renderer.note(bill_section_text.trim())
+            )
         }
         // Inserting line(s)
         else if is_lines {
-            println!("Inserting Line: line numbers are not included in the online version of the law, and thus cannot be accurately included. Bill section will be added as a footnote.");
-            marked_text = format!("{}\n\n_{}_", law_section_text, bill_section_text.trim())
+            let inserted_lines = markup_regex
+                .insert_lines
+                .captures(bill_section_text.as_ref())
+                .ok()
+                .flatten()
+                .and_then(|caps| {
+                    let preceding_line =
+                        get_lines(line_index?, &caps[1], None, &markup_regex.punctuation)?;
+                    Some((preceding_line, String::from(caps[2].trim())))
+                })
+                .filter(|(preceding_line, _)| law_section_text.contains(preceding_line));
+            if let Some((preceding_line, inserted_words)) = inserted_lines {
+                // Insert after the line
+                let replacement = format!(
+                    "{preceding_line} {}{footnote}",
+                    renderer.insert(&inserted_words)
+                );
+
+                marked_text = law_section_text.replacen(&preceding_line, &replacement, 1)
+            } else {
+                warn!("{context} inserting lines: the online law has no line numbers, footnoting");
+                warnings.push(Warning::new(
+                    WarningKind::LineReference,
+                    bill_section_number,
+                    law_chapter_key,
+                ));
+                marked_text = format!(
+                    "{}\n\n{}",
+                    law_section_text,
+                    renderer.note(bill_section_text.trim())
+                )
+            }
         }
         // Inserting section(s)
         else if is_sections {
@@ -305,46 +1126,381 @@ fn mark_text(
                 let matches: Vec<_> = markup_regex
                     .match_sections
                     .find_iter(&section_text)
-                    .map(|m| m.expect("BAD REGEX").as_str().trim())
+                    .map(|m| {
+                        let inserted_section = m.expect("BAD REGEX").as_str().trim();
+                        // Head each inserted section with its designation, such as "Section 5A"
+                        match markup_regex.inserted_section.captures(inserted_section) {
+                            Ok(Some(caps)) => {
+                                let heading = renderer
+                                    .heading(2, &renderer.insert(&format!("Section {}", &caps[1])));
+                                let body = caps[2].trim();
+                                if body.is_empty() {
+                                    format!("{heading}{footnote}")
+                                } else {
+                                    format!("{heading}\n\n{}{footnote}", renderer.insert(body))
+                                }
+                            }
+                            _ => format!("{}{footnote}", renderer.insert(inserted_section)),
+                        }
+                    })
                     .collect();
-                let sep = format!("##^{bill_section_number}^\n\n[.blue]##");
-                let insert = matches.join(sep.as_ref());
-                // Format replacement
-                marked_text = format!(
-                    "\
-                        {law_section_text}\n\n[.blue]##{insert}##^{bill_section_number}^\
-                        "
-                )
+                let insert = matches.join("\n\n");
+
+                // Note where the bill places the inserted sections, such as "after section 5",
+                // and place them before the law section if the bill inserts them before it
+                match markup_regex
+                    .insert_position
+                    .captures(bill_section_text.as_ref())
+                {
+                    Ok(Some(caps)) => {
+                        let note = renderer.note(&format!("Inserted {}", &caps[1]));
+                        if caps.get(2).map(|m| m.as_str()) == Some("before") {
+                            marked_text = format!("{note}\n\n{insert}\n\n{law_section_text}")
+                        } else {
+                            marked_text = format!("{law_section_text}\n\n{note}\n\n{insert}")
+                        }
+                    }
+                    _ if is_adding => {
+                        let note = renderer.note("Added at the end thereof");
+                        marked_text = format!("{law_section_text}\n\n{note}\n\n{insert}")
+                    }
+                    _ => marked_text = format!("{law_section_text}\n\n{insert}"),
+                }
             }
         }
     } else {
-        println!("Not sure what section does: {}", &*law_section_text);
+        warn!("{context} not sure what the bill section does, leaving the law text unmarked");
+        warnings.push(Warning::new(
+            WarningKind::Unrecognized,
+            bill_section_number,
+            law_chapter_key,
+        ));
     }
     marked_text
 }
 
-pub(crate) fn get_adoc_paths(dir: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-    let paths = WalkDir::new(dir)
+pub fn get_paths_with_extension(
+    dir: &str,
+    extension: &str,
+    recursive: bool,
+) -> Result<Vec<PathBuf>, SpringbokError> {
+    let walk_dir = match recursive {
+        true => WalkDir::new(dir),
+        false => WalkDir::new(dir).max_depth(1),
+    };
+    let paths = walk_dir
         .into_iter()
         // Filter out all those directory entries which couldn't be read
         .filter_map(|res| res.ok())
+        // Filter out all directories
+        .filter(|dir_entry| dir_entry.file_type().is_file())
         // Map the directory entries to paths
         .map(|dir_entry| dir_entry.path().to_path_buf())
-        // Filter out all paths with extensions other than `adoc`
-        .filter_map(|path| {
-            if path.extension().map_or(false, |ext| ext == "adoc") {
-                Some(path)
-            } else {
-                None
-            }
-        })
+        // Filter out all paths with other extensions
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
         .collect::<Vec<_>>();
     Ok(paths)
 }
 
-pub fn format_multiline_tags(s: &String) -> String {
+pub fn parse_subsection_chain(
+    bill_section_text: &str,
+    markup_regex: &MarkupRegex,
+) -> Option<SubsectionChain> {
+    // Such as "striking out clause (iii) of subsection (b)", which names the innermost first
+    let caps = markup_regex
+        .nested_subsection
+        .captures(bill_section_text)
+        .ok()
+        .flatten()?;
+    let mut labels: Vec<String> = markup_regex
+        .subsection_label
+        .captures_iter(&caps[1])
+        .filter_map(|caps| Some(caps.ok()?[1].to_string()))
+        .collect();
+    labels.reverse();
+    Some(SubsectionChain { labels })
+}
+
+pub fn find_nested_subsection(
+    law_section_text: &str,
+    subsection_chain: &SubsectionChain,
+    markup_regex: &MarkupRegex,
+) -> Option<Range<usize>> {
+    // Descend from the outermost label, finding each label within the block of the last
+    let mut range = 0..law_section_text.len();
+    for (depth, label) in subsection_chain.labels.iter().enumerate() {
+        let block = &law_section_text[range.clone()];
+        let start = find_label_start(block, label, markup_regex)?;
+
+        // A block ends at its next sibling, or a bracketed note, or the end of its parent
+        let after_label = start + label.len() + 2;
+        let next_start = get_next_label(label, depth)
+            .and_then(|next_label| {
+                find_label_start(&block[after_label..], &next_label, markup_regex)
+            })
+            .map(|next_start| after_label + next_start);
+        let note_start = block[after_label..]
+            .find("\n[")
+            .map(|note_start| after_label + note_start);
+        let end = [next_start, note_start]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(block.len());
+        let end = start + block[start..end].trim_end().len();
+        range = range.start + start..range.start + end;
+    }
+    Some(range)
+}
+
+fn find_label_start(text: &str, label: &str, markup_regex: &MarkupRegex) -> Option<usize> {
+    // Find the first label which begins a line or a clause, and is the label wanted
+    markup_regex
+        .label_start
+        .captures_iter(text)
+        .filter_map(Result::ok)
+        .find(|caps| caps[1].eq_ignore_ascii_case(label))
+        .and_then(|caps| Some(caps.get(1)?.start() - 1))
+}
+
+fn get_next_label(label: &str, depth: usize) -> Option<String> {
+    // Numbers count up, nested roman numerals count up, and letters, including doubled letters
+    // such as "aa", step to the next letter
+    if let Ok(number) = label.parse::<u32>() {
+        return Some((number + 1).to_string());
+    }
+    let label = label.to_lowercase();
+    if depth > 0 {
+        if let Some(number) = parse_roman(&label) {
+            return Some(format_roman(number + 1));
+        }
+    }
+    let letter = label.chars().next()?;
+    if !letter.is_ascii_lowercase() || letter == 'z' || label.chars().any(|c| c != letter) {
+        return None;
+    }
+    Some(((letter as u8 + 1) as char).to_string().repeat(label.len()))
+}
+
+const ROMAN_NUMERALS: [(usize, &str); 8] = [
+    (90, "xc"),
+    (50, "l"),
+    (40, "xl"),
+    (10, "x"),
+    (9, "ix"),
+    (5, "v"),
+    (4, "iv"),
+    (1, "i"),
+];
+
+fn format_roman(mut number: usize) -> String {
+    let mut roman = String::new();
+    for (value, numeral) in ROMAN_NUMERALS {
+        while number >= value {
+            roman.push_str(numeral);
+            number -= value;
+        }
+    }
+    roman
+}
+
+fn parse_roman(roman: &str) -> Option<usize> {
+    // Accept only numerals which format back the same way, such as "iv", but not "iiii"
+    (1..100).find(|number| format_roman(*number) == roman)
+}
+
+fn get_words_regex(words: &str) -> Regex {
+    // Match the words whole, so striking "motor" leaves "motorcycle", letting words which start
+    // or end with punctuation, such as ", and", match next to anything
+    let start = if words.starts_with(char::is_alphanumeric) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if words.ends_with(char::is_alphanumeric) {
+        r"\b"
+    } else {
+        ""
+    };
+    Regex::new(&format!("{start}{}{end}", fancy_regex::escape(words))).unwrap()
+}
+
+fn get_subsection_regex(subsection_label: &str) -> Regex {
+    // Match a subsection by its label, such as "(b)", "(aa)", or "(10)", up to the next
+    // subsection labeled the same way, so numbered clauses within lettered subsections, and
+    // lettered clauses within numbered subsections, are kept, or up to a bracketed note
+    let next_label = if subsection_label.chars().all(|c| c.is_ascii_digit()) {
+        r"\d+"
+    } else {
+        r"[^\d\W]+"
+    };
+    let subsection_regex_string = format!(
+        r"(?i)(\n|^)(section \d+.\s*)?(\({}\))([\s\S]*?)\n(\[.*\]|\({next_label}\))",
+        fancy_regex::escape(subsection_label)
+    );
+    Regex::new(subsection_regex_string.as_ref()).unwrap()
+}
+
+fn find_occurrence(
+    law_section_text: &str,
+    words: &str,
+    is_figure: bool,
+    bill_section_text: &str,
+    line_index: Option<&[String]>,
+    markup_regex: &MarkupRegex,
+) -> Option<usize> {
+    // Find the start of the occurrence of the words in the law text which the bill section
+    // refers to, using a qualifier from the bill section when the words occur more than once
+    let starts: Vec<usize> = law_section_text
+        .match_indices(words)
+        .map(|(start, _)| start)
+        // A figure must not be part of a longer number, such as "50" in "150" or "500"
+        .filter(|start| {
+            !is_figure
+                || !(law_section_text[..*start].ends_with(|c: char| c.is_ascii_digit())
+                    || law_section_text[start + words.len()..]
+                        .starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .collect();
+    if starts.len() <= 1 {
+        return starts.first().copied();
+    }
+
+    // Such as "the second time it appears"
+    if let Ok(Some(caps)) = markup_regex.occurrence.captures(bill_section_text) {
+        let ordinal = [
+            "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+            "tenth",
+        ]
+        .iter()
+        .position(|ordinal| *ordinal == &caps[1])?;
+        return starts.get(ordinal).copied();
+    }
+
+    // Such as "in line 12", when the line can be found
+    let line_range = markup_regex
+        .in_line
+        .captures(bill_section_text)
+        .ok()
+        .flatten()
+        .and_then(|caps| get_lines(line_index?, &caps[1], None, &markup_regex.punctuation))
+        .and_then(|line| {
+            let line_start = law_section_text.find(&line)?;
+            Some(line_start..line_start + line.len())
+        });
+
+    // Such as "in subsection (b)", up to the next subsection
+    let subsection_range = || {
+        let caps = markup_regex
+            .in_subsection
+            .captures(bill_section_text)
+            .ok()
+            .flatten()?;
+        let subsection_start = law_section_text.find(&format!("({})", &caps[1]))?;
+        let subsection_end = law_section_text[subsection_start + 1..]
+            .find("\n(")
+            .map_or(law_section_text.len(), |end| subsection_start + 1 + end);
+        Some(subsection_start..subsection_end)
+    };
+
+    // Use the occurrence only if it is the one occurrence in the qualified range
+    let range = line_range.or_else(subsection_range)?;
+    let range_starts: Vec<usize> = starts
+        .into_iter()
+        .filter(|start| range.contains(start) && start + words.len() <= range.end)
+        .collect();
+    match range_starts[..] {
+        [start] => Some(start),
+        _ => None,
+    }
+}
+
+fn get_lines(
+    line_index: &[String],
+    start_line: &str,
+    end_line: Option<&str>,
+    punctuation_regex: &PunctuationRegex,
+) -> Option<String> {
+    // Resolve one-based, inclusive line numbers against the line index
+    let start_line = start_line.parse::<usize>().ok()?;
+    let end_line = match end_line {
+        Some(end_line) if !end_line.is_empty() => end_line.parse::<usize>().ok()?,
+        _ => start_line,
+    };
+    if start_line == 0 || end_line < start_line || end_line > line_index.len() {
+        return None;
+    }
+    // Normalize the lines as the law and bill text are, so replacements built from either match
+    Some(normalize_punctuation(
+        &normalize_quotes(&line_index[start_line - 1..end_line].join(" ")),
+        punctuation_regex,
+    ))
+}
+
+pub fn escape_asciidoc(text: &str) -> String {
+    // Replace characters asciidoc treats specially with character references, in one pass so
+    // that references are not themselves escaped
+    let mut escaped_text = String::with_capacity(text.len());
+    let mut is_line_start = true;
+    for c in text.chars() {
+        match c {
+            '*' => escaped_text.push_str("&#42;"),
+            '+' => escaped_text.push_str("&#43;"),
+            '_' => escaped_text.push_str("&#95;"),
+            '|' => escaped_text.push_str("&#124;"),
+            '#' => escaped_text.push_str("&#35;"),
+            '`' => escaped_text.push_str("&#96;"),
+            '^' => escaped_text.push_str("&#94;"),
+            '~' => escaped_text.push_str("&#126;"),
+            '.' if is_line_start => escaped_text.push_str("&#46;"),
+            _ => escaped_text.push(c),
+        }
+        is_line_start = c == '\n';
+    }
+    escaped_text
+}
+
+pub fn normalize_quotes(text: &str) -> String {
+    // Replace curly quotes and primes with straight quotes, and non-breaking spaces with spaces
+    text.chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => ' ',
+            _ => c,
+        })
+        .collect()
+}
+
+pub fn normalize_punctuation(text: &str, punctuation_regex: &PunctuationRegex) -> String {
+    // Replace the drafting punctuation ":—" and its other dash styles with ":-", and section
+    // symbols with words, as the markup and law section regexes expect
+    let text = punctuation_regex.colon_dash.replace_all(text, ":-");
+    let text = punctuation_regex.sections.replace_all(&text, "sections ");
+    punctuation_regex
+        .section
+        .replace_all(&text, "section ")
+        .into_owned()
+}
+
+pub fn escape_html(text: &str) -> String {
+    // Replace characters HTML treats specially with character references, leaving quotes, which
+    // need no escaping in text, to be matched by the markup regexes
+    let mut escaped_text = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped_text.push_str("&amp;"),
+            '<' => escaped_text.push_str("&lt;"),
+            '>' => escaped_text.push_str("&gt;"),
+            _ => escaped_text.push(c),
+        }
+    }
+    escaped_text
+}
+
+pub fn format_multiline_tags(s: &str, strike_role: &str) -> String {
     // Apply tags to each paragraph and trim spaces where needed
-    let formatted_string = s.replace("\n\n", "##\n\n[.line-through .red]##");
+    let formatted_string = s.replace("\n\n", &format!("##\n\n[.line-through .{strike_role}]##"));
 
     formatted_string
 }
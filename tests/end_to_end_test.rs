@@ -0,0 +1,269 @@
+use clap::Parser;
+use springbok_mgl::ma_legislature::get_search_page;
+use springbok_mgl::{
+    create_bill, create_law_sections_text, create_refiner_map, create_search_results_map,
+    format_selector_checks, init_config, run_doctor, write_asciidocs, write_bill, Cli, FileNaming,
+    MarkupFormat, MarkupStyle, SelectorCheck, LAW_FOLDER,
+};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+    path::Path,
+    thread,
+};
+use url::Url;
+
+fn get_fixture_name(url: &Url) -> Option<String> {
+    // Map each page the site serves to a saved fixture page
+    match url.path() {
+        "/Bills/Search" => Some(String::from("search.html")),
+        "/Bills/193/H4072" => Some(String::from("bill.html")),
+        "/Bills/193/H4072/Text" => Some(String::from("bill-text.html")),
+        "/GeneralLaws/GoTo" => {
+            let query_value = |key: &str| {
+                url.query_pairs()
+                    .find(|(query_key, _)| query_key == key)
+                    .map(|(_, value)| value.into_owned())
+            };
+            Some(format!(
+                "law-{}-{}.html",
+                query_value("ChapterGoTo")?,
+                query_value("SectionGoTo")?
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn serve_fixtures() -> Url {
+    // Serve saved fixture pages until the test ends, or respond not found
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind listener");
+    let base_url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let server_url = base_url.clone();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buffer = [0; 4096];
+            let length = stream.read(&mut buffer).unwrap_or(0);
+            let request = String::from_utf8_lossy(&buffer[..length]);
+            let body = request
+                .split_whitespace()
+                .nth(1)
+                .and_then(|path| server_url.join(path).ok())
+                .and_then(|url| get_fixture_name(&url))
+                .and_then(|name| {
+                    fs::read_to_string(Path::new("./tests/test-data/site").join(name)).ok()
+                });
+            let response = match body {
+                Some(body) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                ),
+                None => String::from(
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                ),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    base_url
+}
+
+#[test]
+fn it_marks_up_bill_from_search() {
+    let base_url = serve_fixtures();
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--search-term",
+        "H.4072",
+        "--base-url",
+        base_url.as_str(),
+        "--retries",
+        "0",
+    ]);
+    let config = init_config(&cli).expect("Could not init config");
+
+    // Search
+    let (search_results_map, search_term) =
+        create_search_results_map(&cli, &config).expect("Could not search");
+    assert_eq!(search_term, "H.4072");
+    let search_entry = search_results_map
+        .get("H.4072")
+        .expect("Could not find bill in search results");
+    assert_eq!(
+        search_entry.bill_url,
+        base_url.join("/Bills/193/H4072").unwrap()
+    );
+
+    // Bill
+    let (bill, section_counts) =
+        create_bill(&config, &search_term, search_entry).expect("Could not create bill");
+    assert_eq!(bill.title, "An Act relative to town clerks");
+    assert_eq!(bill.sponsor, "Jane Doe");
+    assert_eq!(section_counts.total, 3);
+    assert_eq!(section_counts.amending_by_striking_and_inserting, 1);
+    assert_eq!(section_counts.repealing, 1);
+    assert_eq!(section_counts.effective_date, 1);
+
+    // Law sections
+    let (law_sections_text, law_section_failures) =
+        create_law_sections_text(&config, &bill.bill_sections, 2, true, None, false);
+    assert!(law_section_failures.is_empty());
+    assert_eq!(law_sections_text.len(), 2);
+
+    // Markup
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    write_bill(&bill.bill_sections, "H.4072.txt", output_folder).expect("Could not write bill");
+    write_asciidocs(
+        law_sections_text,
+        &bill.bill_sections,
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Adoc,
+        false,
+    )
+    .expect("Could not write asciidocs");
+
+    let law_folder = Path::new(output_folder).join(LAW_FOLDER);
+    assert_eq!(
+        fs::read_to_string(law_folder.join("41-5.adoc")).unwrap(),
+        "=== Section 5: Town clerks; election\n\nSection 5. The town shall elect a clerk, who shall serve for [.line-through .red]##three years## [.blue]##five years##^1^."
+    );
+    assert_eq!(
+        fs::read_to_string(law_folder.join("40-7.adoc")).unwrap(),
+        "=== Section 7: Town meetings\n\n[.line-through .red]##Section 7. The town shall hold an annual town meeting.##^2^\n\nREPEALED ."
+    );
+    let document = fs::read_to_string(Path::new(output_folder).join("H.4072.adoc")).unwrap();
+    let chapter_40 = document.find("== Chapter 40").unwrap();
+    let chapter_41 = document.find("== Chapter 41").unwrap();
+    assert!(chapter_40 < chapter_41);
+    assert!(Path::new(output_folder).join("H.4072.txt").is_file());
+}
+
+#[test]
+fn it_searches_each_general_court() {
+    let base_url = serve_fixtures();
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--search-term",
+        "H.4072",
+        "--general-court",
+        "193rd,192nd",
+        "--base-url",
+        base_url.as_str(),
+        "--retries",
+        "0",
+    ]);
+    let config = init_config(&cli).expect("Could not init config");
+
+    // Results are keyed by court and bill number, in the order the courts were given
+    let (search_results_map, _) =
+        create_search_results_map(&cli, &config).expect("Could not search");
+    let keys: Vec<&String> = search_results_map.keys().collect();
+    assert_eq!(keys, vec!["193rd:H.4072", "192nd:H.4072"]);
+}
+
+#[test]
+fn it_filters_by_filing_date_without_refining_the_search() {
+    let base_url = serve_fixtures();
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--list",
+        "--since",
+        "2024-01-01",
+        "--base-url",
+        base_url.as_str(),
+        "--retries",
+        "0",
+    ]);
+    let config = init_config(&cli).expect("Could not init config");
+
+    // The search page lists no filing date refiner, so none is added to the search URL
+    let refiner_map = create_refiner_map(&config, None).expect("Could not parse refiners");
+    assert!(refiner_map
+        .keys()
+        .all(|group_label| !group_label.to_lowercase().contains("date")));
+    let (_, search_urls, _) = get_search_page(&cli, &config).expect("Could not get search page");
+    assert!(search_urls.iter().all(|(_, search_url)| !search_url
+        .query_pairs()
+        .any(|(key, _)| key.contains("date"))));
+
+    // Instead, the results are filtered by the date in the "Date Filed" column
+    let (search_results_map, _) =
+        create_search_results_map(&cli, &config).expect("Could not create search results");
+    assert_eq!(
+        search_results_map["H.4072"].filed_date.as_deref(),
+        Some("2024-01-05")
+    );
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--list",
+        "--until",
+        "2023-12-31",
+        "--base-url",
+        base_url.as_str(),
+        "--retries",
+        "0",
+    ]);
+    let (search_results_map, _) =
+        create_search_results_map(&cli, &config).expect("Could not create search results");
+    assert!(search_results_map.is_empty());
+}
+
+#[test]
+fn it_checks_selectors_against_the_site() {
+    let base_url = serve_fixtures();
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--doctor",
+        "--base-url",
+        base_url.as_str(),
+        "--retries",
+        "0",
+    ]);
+    let config = init_config(&cli).expect("Could not init config");
+    let checks = run_doctor(&config);
+    assert!(checks.iter().all(SelectorCheck::passed));
+    assert_eq!(
+        format_selector_checks(&checks[..2]),
+        "PASS search page: refiners (div#refiners) matched 1 element(s)\n\
+         PASS search page: refiner groups (div#refiners fieldset) matched 6 element(s)\n"
+    );
+
+    // A selector which no longer matches fails, but the other pages are still checked
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--doctor",
+        "--base-url",
+        base_url.as_str(),
+        "--retries",
+        "0",
+        "--law-heading-selector",
+        "h2#missing",
+    ]);
+    let config = init_config(&cli).expect("Could not init config");
+    let checks = run_doctor(&config);
+    let failed: Vec<&str> = checks
+        .iter()
+        .filter(|check| !check.passed())
+        .map(|check| check.element.as_str())
+        .collect();
+    assert_eq!(failed, vec!["law text"]);
+
+    // Pages linked from a page which can't be fetched are not checked
+    let config = init_config(&Cli::parse_from([
+        "springbok-mgl",
+        "--base-url",
+        base_url.join("missing/").unwrap().as_str(),
+        "--retries",
+        "0",
+    ]))
+    .expect("Could not init config");
+    let checks = run_doctor(&config);
+    assert!(checks.iter().all(|check| check.count.is_err()));
+    assert!(format_selector_checks(&checks)
+        .starts_with("FAIL search page: refiners (div#refiners) not checked: Couldn't get"));
+}
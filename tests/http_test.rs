@@ -0,0 +1,207 @@
+use reqwest::StatusCode;
+use springbok_mgl::{
+    build_client, check_deadline, fetch, fetch_law_section, get_retry_delay, is_retryable_status,
+    Config, RateLimiter, SpringbokError, DEFAULT_USER_AGENT,
+};
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::Arc,
+    thread,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+fn serve_statuses(statuses: Vec<u16>) -> (Url, JoinHandle<usize>) {
+    // Serve one response per status, in order, then return the number of requests served
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind listener");
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let handle = thread::spawn(move || {
+        let mut request_count = 0;
+        for status in statuses {
+            let (mut stream, _) = listener.accept().expect("Could not accept connection");
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer);
+            let body = format!("status {status}");
+            let response = format!(
+                "HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            request_count += 1;
+        }
+        request_count
+    });
+    (url, handle)
+}
+
+fn test_config(base_url: &Url, retries: u32) -> Config {
+    Config {
+        base_url: base_url.clone(),
+        retries,
+        retry_base_delay: Duration::from_millis(1),
+        client: build_client(Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap(),
+        rate_limiter: Arc::new(RateLimiter::new(0.0).unwrap()),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn it_retries_server_errors() {
+    let (url, handle) = serve_statuses(vec![500, 503, 200]);
+    let body = fetch(&test_config(&url, 3), &url).expect("Could not fetch after retries");
+    assert_eq!(body, "status 200");
+    assert_eq!(handle.join().unwrap(), 3);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn it_retries_server_errors_asynchronously() {
+    use springbok_mgl::{build_async_client, fetch_async};
+
+    let (url, handle) = serve_statuses(vec![500, 503, 200]);
+    let config = test_config(&url, 3);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let body = runtime
+        .block_on(async {
+            let client = build_async_client(Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap();
+            fetch_async(&config, &client, &url).await
+        })
+        .expect("Could not fetch after retries");
+    assert_eq!(body, "status 200");
+    assert_eq!(handle.join().unwrap(), 3);
+}
+
+#[test]
+fn it_gives_up_after_retries() {
+    let (url, handle) = serve_statuses(vec![500, 500]);
+    assert!(fetch(&test_config(&url, 1), &url).is_err());
+    assert_eq!(handle.join().unwrap(), 2);
+}
+
+#[test]
+fn it_does_not_retry_not_found() {
+    let (url, handle) = serve_statuses(vec![404]);
+    assert!(fetch(&test_config(&url, 3), &url).is_err());
+    assert_eq!(handle.join().unwrap(), 1);
+}
+
+#[test]
+fn it_distinguishes_missing_law_sections_from_network_errors() {
+    let (url, handle) = serve_statuses(vec![404, 404]);
+    let config = test_config(&url, 3);
+    match fetch(&config, &url) {
+        Err(SpringbokError::Network {
+            status: Some(404), ..
+        }) => {}
+        result => panic!("Expected a network error, got {result:?}"),
+    }
+    match fetch_law_section(&config, "40", "7", None) {
+        Err(SpringbokError::LawSectionNotFound {
+            law_chapter,
+            law_section,
+            ..
+        }) => assert_eq!((law_chapter.as_str(), law_section.as_str()), ("40", "7")),
+        result => panic!("Expected a missing law section, got {result:?}"),
+    }
+    assert_eq!(handle.join().unwrap(), 2);
+
+    let config = Config {
+        deadline: Some(Instant::now()),
+        ..test_config(&url, 0)
+    };
+    match check_deadline(&config, "downloading") {
+        Err(SpringbokError::DeadlineExceeded { stage }) => assert_eq!(stage, "downloading"),
+        result => panic!("Expected the deadline to be exceeded, got {result:?}"),
+    }
+}
+
+#[test]
+fn it_times_out_stalled_requests() {
+    // Accept connections but never respond
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind listener");
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let _handle = thread::spawn(move || {
+        let _streams: Vec<_> = listener.incoming().collect();
+    });
+    let config = Config {
+        client: build_client(Duration::from_millis(100), DEFAULT_USER_AGENT).unwrap(),
+        ..test_config(&url, 0)
+    };
+    let start = Instant::now();
+    assert!(fetch(&config, &url).is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn it_classifies_retryable_statuses() {
+    assert!(is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+    assert!(is_retryable_status(StatusCode::BAD_GATEWAY));
+    assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+    assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+    assert!(!is_retryable_status(StatusCode::FORBIDDEN));
+}
+
+#[test]
+fn it_limits_request_rate_across_threads() {
+    let rate_limiter = Arc::new(RateLimiter::new(100.0).unwrap());
+    let start = Instant::now();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let rate_limiter = Arc::clone(&rate_limiter);
+            thread::spawn(move || {
+                for _ in 0..5 {
+                    rate_limiter.wait();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    // Twenty requests at 100 per second take at least 190 ms, however many threads make them
+    assert!(start.elapsed() >= Duration::from_millis(190));
+
+    let unlimited = RateLimiter::new(0.0).unwrap();
+    assert!((0..100).all(|_| unlimited.reserve().is_zero()));
+    assert!(RateLimiter::new(-1.0).is_err());
+}
+
+#[test]
+fn it_backs_off_exponentially() {
+    let base_delay = Duration::from_millis(100);
+    for attempt in 0..4 {
+        let delay = get_retry_delay(base_delay, attempt);
+        let expected = base_delay * 2u32.pow(attempt);
+        assert!(delay >= expected && delay <= expected.mul_f64(1.5));
+    }
+}
+
+#[test]
+fn it_sends_user_agent() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind listener");
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("Could not accept connection");
+        let mut buffer = [0; 1024];
+        let length = stream.read(&mut buffer).unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        String::from_utf8_lossy(&buffer[..length]).to_lowercase()
+    });
+    let config = Config {
+        client: build_client(Duration::from_secs(5), "springbok-test/1.0").unwrap(),
+        ..test_config(&url, 0)
+    };
+    fetch(&config, &url).expect("Could not fetch");
+    assert!(handle
+        .join()
+        .unwrap()
+        .contains("user-agent: springbok-test/1.0"));
+    assert!(DEFAULT_USER_AGENT.starts_with("springbok-mgl/0.1.0 (+https://"));
+}
@@ -1,4 +1,3 @@
-use springbok_mgl;
 use springbok_mgl::{
     collect_bill_sections, count_bill_section_types, init_section_regex, SectionCounts,
 };
@@ -45,6 +44,16 @@ fn assert_section_counts(section_counts: SectionCounts, expected_section_counts:
         "Expected section count repealing was '{}', actual result was '{}'",
         expected_section_counts.repealing, section_counts.repealing
     );
+    assert_eq!(
+        section_counts.effective_date, expected_section_counts.effective_date,
+        "Expected section count effective_date was '{}', actual result was '{}'",
+        expected_section_counts.effective_date, section_counts.effective_date
+    );
+    assert_eq!(
+        section_counts.appropriation, expected_section_counts.appropriation,
+        "Expected section count appropriation was '{}', actual result was '{}'",
+        expected_section_counts.appropriation, section_counts.appropriation
+    );
     assert_eq!(
         section_counts.other, expected_section_counts.other,
         "Expected section count other was '{}', actual result was '{}'",
@@ -58,7 +67,11 @@ fn assert_section_counts(section_counts: SectionCounts, expected_section_counts:
     );
     assert_eq!(
         section_counts.total,
-        section_counts.amending + section_counts.repealing + section_counts.other
+        section_counts.amending
+            + section_counts.repealing
+            + section_counts.effective_date
+            + section_counts.appropriation
+            + section_counts.other
     );
 }
 
@@ -71,11 +84,13 @@ fn it_counts_hd4607() {
         amending_by_striking: 17,
         amending_by_inserting: 9,
         repealing: 3,
-        other: 10,
+        effective_date: 3,
+        appropriation: 0,
+        other: 7,
     };
     let text_nodes = nodes_from_file("./tests/test-data/HD.4607.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -89,11 +104,13 @@ fn it_counts_h4072() {
         amending_by_striking: 0,
         amending_by_inserting: 0,
         repealing: 0,
-        other: 3,
+        effective_date: 1,
+        appropriation: 0,
+        other: 2,
     };
     let text_nodes = nodes_from_file("./tests/test-data/H.4072.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -108,11 +125,13 @@ fn it_counts_h4072_lower() {
         amending_by_striking: 0,
         amending_by_inserting: 0,
         repealing: 0,
-        other: 3,
+        effective_date: 1,
+        appropriation: 0,
+        other: 2,
     };
     let text_nodes = nodes_from_file("./tests/test-data/H.4072.lower.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -126,11 +145,13 @@ fn it_counts_s2482() {
         amending_by_striking: 1,
         amending_by_inserting: 1,
         repealing: 0,
+        effective_date: 0,
+        appropriation: 0,
         other: 2,
     };
     let text_nodes = nodes_from_file("./tests/test-data/S.2482.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -144,11 +165,13 @@ fn it_counts_h4220() {
         amending_by_striking: 0,
         amending_by_inserting: 0,
         repealing: 0,
-        other: 1,
+        effective_date: 1,
+        appropriation: 0,
+        other: 0,
     };
     let text_nodes = nodes_from_file("./tests/test-data/H.4220.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -162,11 +185,13 @@ fn it_counts_sd2897() {
         amending_by_striking: 0,
         amending_by_inserting: 1,
         repealing: 0,
+        effective_date: 0,
+        appropriation: 0,
         other: 0,
     };
     let text_nodes = nodes_from_file("./tests/test-data/SD.2897.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -180,11 +205,13 @@ fn it_counts_hd4741() {
         amending_by_striking: 0,
         amending_by_inserting: 0,
         repealing: 0,
+        effective_date: 0,
+        appropriation: 0,
         other: 1,
     };
     let text_nodes = nodes_from_file("./tests/test-data/HD.4741.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -198,11 +225,13 @@ fn it_counts_h47() {
         amending_by_striking: 0,
         amending_by_inserting: 0,
         repealing: 0,
-        other: 3,
+        effective_date: 0,
+        appropriation: 2,
+        other: 1,
     };
     let text_nodes = nodes_from_file("./tests/test-data/H.47.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -217,11 +246,13 @@ fn it_counts_h47_lower() {
         amending_by_striking: 0,
         amending_by_inserting: 0,
         repealing: 0,
-        other: 3,
+        effective_date: 0,
+        appropriation: 2,
+        other: 1,
     };
     let text_nodes = nodes_from_file("./tests/test-data/H.47.lower.txt");
     let section_regex = init_section_regex();
-    let bill = collect_bill_sections(text_nodes, &section_regex);
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
     let section_counts = count_bill_section_types(&bill, &section_regex);
     assert_section_counts(section_counts, expected_section_counts);
 }
@@ -0,0 +1,292 @@
+use springbok_mgl::{
+    collect_bill_sections, collect_law_sections, compare_law_numbers, compare_law_sections,
+    expand_section_range, fetch_law_section, format_bill_sections, format_law_section, get_law_url,
+    get_section_key, init_bill_section_regex, log_context, parse_law_text, run_bounded,
+    write_cache, Cache, Config, LawSource, LawTextSelectors, SpringbokError,
+};
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use url::Url;
+
+#[test]
+fn it_bounds_concurrent_downloads() {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let completed = Arc::new(AtomicUsize::new(0));
+    let handles = {
+        let in_flight = Arc::clone(&in_flight);
+        let max_in_flight = Arc::clone(&max_in_flight);
+        let completed = Arc::clone(&completed);
+        run_bounded((0..20).collect(), 3, move |_: i32| {
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(current, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            completed.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(completed.load(Ordering::SeqCst), 20);
+    assert!(max_in_flight.load(Ordering::SeqCst) <= 3);
+}
+
+#[test]
+fn it_collects_section_ranges() {
+    let section_str = "SECTION 3. Chapter 40 of the General Laws is hereby amended in sections 1 to 5, inclusive, by striking out the word \"town\"";
+    let law_sections = collect_law_sections("3", section_str);
+    assert_eq!(law_sections.chapter_number, "40");
+    assert_eq!(law_sections.section_numbers, vec!["1", "2", "3", "4", "5"]);
+
+    let section_str = "SECTION 4. Chapter 41 of the General Laws is hereby amended in sections 3 through 7 by striking out the word \"city\"";
+    let law_sections = collect_law_sections("4", section_str);
+    assert_eq!(law_sections.chapter_number, "41");
+    assert_eq!(law_sections.section_numbers, vec!["3", "4", "5", "6", "7"]);
+}
+
+#[test]
+fn it_keeps_lettered_section_ranges() {
+    assert_eq!(expand_section_range("6A", "6C"), vec!["6A", "6C"]);
+    assert_eq!(expand_section_range("2", "4"), vec!["2", "3", "4"]);
+}
+
+#[test]
+fn it_collects_sections_delimited_by_and() {
+    let law_sections = collect_law_sections("1", "amended in sections 1, 2 and 3 of chapter 40");
+    assert_eq!(law_sections.chapter_number, "40");
+    assert_eq!(law_sections.section_numbers, vec!["1", "2", "3"]);
+
+    let law_sections = collect_law_sections(
+        "2",
+        "SECTION 2. Chapter 40 of the General Laws is hereby amended in sections 4, 5 & 6A.",
+    );
+    assert_eq!(law_sections.chapter_number, "40");
+    assert_eq!(law_sections.section_numbers, vec!["4", "5", "6A"]);
+}
+
+#[test]
+fn it_handles_lettered_chapters() {
+    let law_sections = collect_law_sections(
+        "5",
+        "SECTION 5. Section 16 of chapter 6A of the General Laws is hereby amended by striking out the word \"shall\"",
+    );
+    assert_eq!(law_sections.chapter_number, "6A");
+    assert_eq!(law_sections.section_numbers, vec!["16"]);
+
+    let config = Config::default();
+    let law_chapter = &law_sections.chapter_number;
+    let law_section = &law_sections.section_numbers[0];
+    assert_eq!(get_section_key(law_chapter, law_section), "6A-16");
+    assert_eq!(
+        get_law_url(&config, law_chapter, law_section).as_str(),
+        "https://malegislature.gov/GeneralLaws/GoTo?ChapterGoTo=6A&SectionGoTo=16"
+    );
+    assert_eq!(
+        get_law_url(&config, "111F", &String::from("3")).as_str(),
+        "https://malegislature.gov/GeneralLaws/GoTo?ChapterGoTo=111F&SectionGoTo=3"
+    );
+}
+
+#[test]
+fn it_skips_laws_other_than_the_general_laws() {
+    let law_sections = collect_law_sections(
+        "2",
+        "SECTION 2. Article XLVIII of the Amendments to the Constitution of the Commonwealth is hereby amended by striking out section 3",
+    );
+    assert_eq!(law_sections.law_source, LawSource::Constitution);
+    assert!(law_sections.section_numbers.is_empty());
+
+    let law_sections = collect_law_sections(
+        "3",
+        "SECTION 3. Section 4 of chapter 123 of the acts of 2022 is hereby amended by striking out the word \"June\" and inserting in place thereof the following word:- July.",
+    );
+    assert_eq!(law_sections.law_source, LawSource::SessionLaws);
+    assert!(law_sections.chapter_number.is_empty());
+    assert!(law_sections.section_numbers.is_empty());
+
+    // A later mention of the constitution or the acts doesn't change the law amended
+    let law_sections = collect_law_sections(
+        "4",
+        "SECTION 4. Section 7 of chapter 40 of the General Laws is hereby amended by inserting after the word \"clerk\" the following words:- , as provided by the constitution and chapter 123 of the acts of 2022",
+    );
+    assert_eq!(law_sections.law_source, LawSource::GeneralLaws);
+    assert_eq!(law_sections.section_numbers, vec!["7"]);
+
+    // Neither is an unresolved reference, and both are noted when dumped
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(
+        &[
+            String::from("SECTION 1. Article XLVIII of the Amendments to the Constitution is hereby amended by striking out section 3."),
+            String::from("SECTION 2. Chapter 123 of the acts of 2022 is hereby repealed."),
+        ],
+        &section_regex,
+    );
+    assert!(bill.iter().all(|bill_section| !bill_section.unresolved));
+    assert_eq!(
+        format_bill_sections(&bill, &section_regex),
+        "SECTION 1 — amending_by_striking — the Constitution, not downloaded\n\
+         SECTION 2 — repealing — the session laws, not downloaded\n"
+    );
+}
+
+#[test]
+fn it_uses_configured_base_url_for_law_url() {
+    let config = Config {
+        base_url: Url::parse("http://localhost:8080").unwrap(),
+        ..Config::default()
+    };
+    assert_eq!(
+        get_law_url(&config, "40", &String::from("7")).as_str(),
+        "http://localhost:8080/GeneralLaws/GoTo?ChapterGoTo=40&SectionGoTo=7"
+    );
+}
+
+#[test]
+fn it_formats_fractions_anywhere_in_law_section() {
+    assert_eq!(format_law_section(&String::from("3¾")), "3 3~4");
+    assert_eq!(format_law_section(&String::from("3 ¾")), "3 3~4");
+    assert_eq!(format_law_section(&String::from("3¾A")), "3 3~4A");
+    assert_eq!(format_law_section(&String::from("5½B")), "5 1~2B");
+    assert_eq!(format_law_section(&String::from("5 1/2")), "5 1~2");
+    assert_eq!(format_law_section(&String::from("12A")), "12A");
+}
+
+const LAW_BODY: &str = r#"<html><body><div class="col-xs-12">
+    <h2 id="skipTo">Section 7: Town clerk</h2>
+    <p>Section 7. The town shall elect a clerk.</p>
+    </div></body></html>"#;
+
+#[test]
+fn it_parses_law_text() {
+    let law_text =
+        parse_law_text(LAW_BODY, &LawTextSelectors::default()).expect("Could not parse law text");
+    assert!(law_text.contains("Section 7: Town clerk"));
+    assert!(law_text.contains("Section 7. The town shall elect a clerk."));
+    assert!(parse_law_text("<html><body></body></html>", &LawTextSelectors::default()).is_err());
+}
+
+#[test]
+fn it_falls_back_through_law_text_selectors() {
+    // A changed layout, without the default heading
+    let html = r#"<html><body><article class="law">
+        <h3>Section 7: Town clerk</h3>
+        <p>Section 7. The town shall elect a clerk.</p>
+        </article></body></html>"#;
+    let error = parse_law_text(html, &LawTextSelectors::default()).unwrap_err();
+    assert!(error.to_string().contains("\"h2#skipTo\""));
+    assert!(matches!(error, SpringbokError::Selector { url: None, .. }));
+
+    let law_text_selectors = LawTextSelectors {
+        heading: vec![String::from("h2#skipTo"), String::from("article.law h3")],
+        container: Vec::new(),
+    };
+    let law_text = parse_law_text(html, &law_text_selectors).expect("Could not parse law text");
+    assert!(law_text.starts_with("Section 7: Town clerk\n\nSection 7."));
+
+    let law_text_selectors = LawTextSelectors {
+        heading: vec![String::from("h2#skipTo")],
+        container: vec![String::from("article.law")],
+    };
+    let law_text = parse_law_text(html, &law_text_selectors).expect("Could not parse law text");
+    assert!(law_text.starts_with("Section 7: Town clerk"));
+
+    let law_text_selectors = LawTextSelectors {
+        heading: vec![String::from("h2[")],
+        container: Vec::new(),
+    };
+    let error = parse_law_text(html, &law_text_selectors).unwrap_err();
+    assert!(error.to_string().contains("Invalid selector \"h2[\""));
+    assert!(matches!(error, SpringbokError::Parse { .. }));
+}
+
+#[test]
+fn it_keeps_law_subsections_apart() {
+    let html = fs::read_to_string("./tests/test-data/law-subsections.html").expect("no such file");
+    let law_text =
+        parse_law_text(&html, &LawTextSelectors::default()).expect("Could not parse law text");
+    assert_eq!(
+        law_text,
+        "Section 7: Town clerk; duties\n\n\
+         Section 7. (a) The town shall elect a clerk, who shall serve for three years.\n\n\
+         (b) The clerk shall keep records of all town meetings.\n\n\
+         (1) Records shall be kept in the town hall.\n\n\
+         (c) The clerk shall certify votes."
+    );
+}
+
+#[test]
+fn it_fetches_law_section_from_cache() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().to_path_buf(),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "40-7.txt",
+        "Section 7. The town shall elect a clerk.",
+    );
+
+    // The cache is used before the network, which is unreachable at this base URL
+    let config = Config {
+        base_url: Url::parse("http://127.0.0.1:9").unwrap(),
+        retries: 0,
+        ..Config::default()
+    };
+    let law_text = fetch_law_section(
+        &config,
+        &String::from("40"),
+        &String::from("7"),
+        Some(&cache),
+    )
+    .expect("Could not fetch law section");
+    assert_eq!(law_text, "Section 7. The town shall elect a clerk.");
+    assert!(fetch_law_section(
+        &config,
+        &String::from("40"),
+        &String::from("8"),
+        Some(&cache)
+    )
+    .is_err());
+}
+
+#[test]
+fn it_sorts_law_numbers_numerically() {
+    let mut sections = vec!["2", "10", "3A", "3"];
+    sections.sort_by(|a, b| compare_law_numbers(a, b));
+    assert_eq!(sections, vec!["2", "3", "3A", "10"]);
+
+    let mut sections = vec!["4A", "4 1/2", "4", "12B", "12"];
+    sections.sort_by(|a, b| compare_law_numbers(a, b));
+    assert_eq!(sections, vec!["4", "4 1/2", "4A", "12", "12B"]);
+
+    // Chapters are ordered before sections
+    let mut law_sections = [
+        (String::from("111F"), String::from("2")),
+        (String::from("40"), String::from("10")),
+        (String::from("40"), String::from("9")),
+        (String::from("6A"), String::from("1")),
+    ];
+    law_sections.sort_by(compare_law_sections);
+    let keys: Vec<String> = law_sections
+        .iter()
+        .map(|(chapter, section)| format!("{chapter}-{section}"))
+        .collect();
+    assert_eq!(keys, vec!["6A-1", "40-9", "40-10", "111F-2"]);
+}
+
+#[test]
+fn it_formats_log_context() {
+    assert_eq!(log_context(Some("12"), Some("40-7")), "[SECTION 12 / 40-7]");
+    assert_eq!(log_context(Some("12"), None), "[SECTION 12]");
+    assert_eq!(log_context(None, Some("40-7")), "[40-7]");
+}
@@ -0,0 +1,1243 @@
+use clap::Parser;
+use log::LevelFilter;
+use springbok_mgl::law_section::download_law_sections;
+use springbok_mgl::{
+    collect_bill_sections, copy_generated_files, create_bill_from_html,
+    create_bill_from_text_nodes, create_law_sections_text, create_manifest,
+    format_asciidoctor_messages, format_manifest_json, format_section_counts_summary,
+    get_bill_numbers, get_bill_text_href, get_bill_text_nodes_from_html,
+    get_bill_text_nodes_from_pdf_text, get_log_level, get_output_folder, get_required_law_sections,
+    init_bill_section_regex, is_pdf_url, is_rendered, pad_law_number, parse_bill_metadata,
+    parse_file_name_template, read_cache, read_text_nodes, remove_generated_files, run_asciidoctor,
+    sanitize_folder_name, write_asciidocs, write_bill, write_cache, write_if_changed,
+    write_manifest, write_section_counts_csv, BillSection, Cache, Cli, Config, DocType, FileNaming,
+    LawSectionWithText, LawSections, LawSource, ManifestEntry, MarkupFormat, MarkupStyle,
+    SearchEntry, SectionCounts, SpringbokError, Warning, WarningKind, LAW_FOLDER, MANIFEST_FILE,
+};
+use std::{
+    fs,
+    fs::File,
+    io,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn it_writes_outputs_to_folder() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    let output_filename = String::from("H.4072.txt");
+
+    write_bill(&Vec::new(), &output_filename, output_folder).expect("Could not write bill");
+    write_asciidocs(
+        Vec::new(),
+        &Vec::new(),
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Adoc,
+        false,
+    )
+    .expect("Could not write asciidocs");
+    assert!(Path::new(output_folder).join(&output_filename).is_file());
+    assert!(Path::new(output_folder).join(LAW_FOLDER).is_dir());
+    assert!(Path::new(output_folder).join("H.4072.adoc").is_file());
+
+    // Only render when asciidoctor is available
+    if Command::new("asciidoctor")
+        .arg("--version")
+        .output()
+        .is_ok()
+    {
+        run_asciidoctor(output_folder, "asciidoctor", false).expect("Could not run asciidoctor");
+        assert!(Path::new(output_folder).join("H.4072.html").is_file());
+    }
+}
+
+#[test]
+fn it_returns_law_sections_which_fail_to_mark_up() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    let law_sections_text = vec![
+        LawSectionWithText {
+            law_chapter_key: String::from("40-7"),
+            text: String::from("Section 7. The town shall elect a clerk."),
+            bill_section_keys: Vec::new(),
+            line_index: None,
+        },
+        // Law text without a section heading can't be parsed
+        LawSectionWithText {
+            law_chapter_key: String::from("41-5"),
+            text: String::from("The clerk shall keep records."),
+            bill_section_keys: Vec::new(),
+            line_index: None,
+        },
+    ];
+    let warnings = write_asciidocs(
+        law_sections_text,
+        &Vec::new(),
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Adoc,
+        false,
+    )
+    .expect("Could not write asciidocs");
+    assert_eq!(
+        warnings,
+        vec![Warning {
+            kind: WarningKind::MarkupFailed,
+            bill_section_number: None,
+            law_chapter_key: Some(String::from("41-5")),
+        }]
+    );
+
+    // Whatever could be marked up is still written
+    let law_folder = Path::new(output_folder).join(LAW_FOLDER);
+    assert!(law_folder.join("40-7.adoc").is_file());
+    assert!(!law_folder.join("41-5.adoc").exists());
+}
+
+#[test]
+fn it_removes_and_copies_only_generated_files() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let adoc_folder = temp_dir.path().join("adoc").join("H.4072");
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    let law_sections_text = vec![LawSectionWithText {
+        law_chapter_key: String::from("40-7"),
+        text: String::from("Section 7. The town shall elect a clerk."),
+        bill_section_keys: Vec::new(),
+        line_index: None,
+    }];
+    let manifest = create_manifest(
+        &Config::default(),
+        &law_sections_text,
+        output_folder,
+        &[MarkupFormat::Adoc, MarkupFormat::Html],
+        &FileNaming::default(),
+        false,
+    );
+    write_asciidocs(
+        law_sections_text,
+        &Vec::new(),
+        adoc_folder.to_str().unwrap(),
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Adoc,
+        false,
+    )
+    .expect("Could not write asciidocs");
+
+    // Stand in for asciidoctor, then copy only the HTML
+    for entry in manifest.iter().filter(|entry| entry.format == "html") {
+        fs::write(adoc_folder.join(&entry.file), "<html></html>").unwrap();
+    }
+    copy_generated_files(&manifest, &adoc_folder, output_folder, MarkupFormat::Html)
+        .expect("Could not copy HTML");
+    let output_path = Path::new(output_folder);
+    assert!(output_path.join(LAW_FOLDER).join("40-7.html").is_file());
+    assert!(output_path.join("H.4072.html").is_file());
+    assert!(!output_path.join("H.4072.adoc").exists());
+
+    // Remove only the asciidoc files written, leaving any others
+    let mut manifest = manifest;
+    fs::write(adoc_folder.join("notes.adoc"), "Notes").unwrap();
+    remove_generated_files(
+        &mut manifest,
+        adoc_folder.to_str().unwrap(),
+        MarkupFormat::Adoc,
+    )
+    .expect("Could not remove asciidocs");
+    assert!(!adoc_folder.join(LAW_FOLDER).join("40-7.adoc").exists());
+    assert!(!adoc_folder.join("H.4072.adoc").exists());
+    assert!(adoc_folder.join("notes.adoc").is_file());
+    assert!(adoc_folder.join("H.4072.html").is_file());
+    assert!(manifest.iter().all(|entry| entry.format == "html"));
+
+    // Asciidoc files are kept by default
+    let cli = Cli::try_parse_from(["springbok-mgl", "--no-keep-adoc", "--keep-adoc"]).unwrap();
+    assert!(!cli.no_keep_adoc);
+    let cli = Cli::try_parse_from(["springbok-mgl", "--keep-adoc", "--no-keep-adoc"]).unwrap();
+    assert!(cli.no_keep_adoc);
+    assert!(Cli::try_parse_from(["springbok-mgl", "--no-keep-adoc", "--no-asciidoctor"]).is_err());
+    assert!(Cli::try_parse_from(["springbok-mgl", "--adoc-temp-dir", "--keep-adoc"]).is_err());
+}
+
+#[test]
+fn it_writes_manifest_of_generated_files() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    fs::create_dir_all(output_folder).unwrap();
+    let law_sections_text = vec![LawSectionWithText {
+        law_chapter_key: String::from("40-7"),
+        text: String::from("Section 7. The town shall elect a clerk."),
+        bill_section_keys: vec![String::from("1"), String::from("3")],
+        line_index: None,
+    }];
+    let manifest = create_manifest(
+        &Config::default(),
+        &law_sections_text,
+        output_folder,
+        &[MarkupFormat::Adoc, MarkupFormat::Html],
+        &FileNaming::default(),
+        false,
+    );
+    let files: Vec<&str> = manifest.iter().map(|entry| entry.file.as_str()).collect();
+    assert_eq!(
+        files,
+        vec![
+            "modified-laws/40-7.adoc",
+            "H.4072.adoc",
+            "modified-laws/40-7.html",
+            "H.4072.html"
+        ]
+    );
+    assert_eq!(manifest[2].format, "html");
+    assert_eq!(manifest[0].bill_section_keys, vec!["1", "3"]);
+    assert_eq!(
+        manifest[0].law_url,
+        "https://malegislature.gov/GeneralLaws/GoTo?ChapterGoTo=40&SectionGoTo=7"
+    );
+
+    write_manifest(&manifest, output_folder).expect("Could not write manifest");
+    let manifest_json: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(Path::new(output_folder).join(MANIFEST_FILE)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(manifest_json[0]["law_chapter_key"], "40-7");
+    assert_eq!(manifest_json[1]["file"], "H.4072.adoc");
+
+    // The manifest is JSON whether or not serde is enabled, escaping what JSON requires
+    let entry = ManifestEntry {
+        file: String::from("say \"hi\"\\\t.adoc"),
+        bill_section_keys: Vec::new(),
+        ..manifest[0].clone()
+    };
+    let entry_json: serde_json::Value =
+        serde_json::from_str(&format_manifest_json(&[entry])).unwrap();
+    assert_eq!(entry_json[0]["file"], "say \"hi\"\\\t.adoc");
+    assert_eq!(entry_json[0]["bill_section_keys"], serde_json::json!([]));
+    assert_eq!(format_manifest_json(&[]), "[]");
+
+    // A single document holds every law section
+    let manifest = create_manifest(
+        &Config::default(),
+        &law_sections_text,
+        output_folder,
+        &[MarkupFormat::Diff],
+        &FileNaming::default(),
+        true,
+    );
+    assert_eq!(manifest.len(), 1);
+    assert_eq!(manifest[0].file, "H.4072.diff");
+}
+
+#[test]
+fn it_errs_when_asciidoctor_fails() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    write_asciidocs(
+        Vec::new(),
+        &Vec::new(),
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Adoc,
+        false,
+    )
+    .expect("Could not write asciidocs");
+
+    // A missing executable suggests skipping rendering
+    let error = run_asciidoctor(output_folder, "no-such-asciidoctor", false).unwrap_err();
+    assert!(matches!(error, SpringbokError::AsciidoctorMissing { .. }));
+    assert!(error.to_string().contains("--no-asciidoctor"));
+
+    // A failing executable is an error, with its exit status
+    match run_asciidoctor(output_folder, "false", false) {
+        Err(SpringbokError::Asciidoctor { status, .. }) => assert!(!status.success()),
+        result => panic!("Expected asciidoctor to fail, got {result:?}"),
+    }
+}
+
+#[test]
+fn it_names_files_with_template() {
+    let file_naming = FileNaming {
+        template: String::from("{court}-{bill}-ch{chapter}-s{section}"),
+        court: String::from("193rd"),
+        bill: String::from("H.4072"),
+        ..FileNaming::default()
+    };
+    assert_eq!(
+        file_naming.law_section_name("40-7"),
+        "193-H.4072-ch040-s007"
+    );
+    assert_eq!(
+        file_naming.law_section_file("6A-12B", "adoc"),
+        format!("{LAW_FOLDER}/193-H.4072-ch006A-s012B.adoc")
+    );
+    assert_eq!(file_naming.document_name("output/H.4072"), "193-H.4072");
+
+    // By default, files are named by law chapter key, and the document after the output folder
+    let file_naming = FileNaming::default();
+    assert_eq!(file_naming.law_section_name("40-7"), "40-7");
+    assert_eq!(file_naming.document_name("output/H.4072"), "H.4072");
+    assert_eq!(pad_law_number("111F"), "111F");
+    assert_eq!(pad_law_number("23½"), "023½");
+
+    assert!(parse_file_name_template("{bill}-{key}").is_ok());
+    assert!(parse_file_name_template("{bill}-{chapter}").is_err());
+    assert!(parse_file_name_template("{docket}-{key}").is_err());
+    assert!(parse_file_name_template("{key").is_err());
+    let cli = Cli::try_parse_from(["springbok-mgl"]).unwrap();
+    assert_eq!(cli.file_name_template, "{key}");
+}
+
+#[test]
+fn it_formats_asciidoctor_messages() {
+    let path = Path::new("H.4072/H.4072.adoc");
+    assert_eq!(format_asciidoctor_messages(path, b"", b"\n"), None);
+    assert_eq!(
+        format_asciidoctor_messages(
+            path,
+            b"",
+            b"asciidoctor: WARNING: H.4072.adoc: line 7: unterminated open block\nasciidoctor: WARNING: H.4072.adoc: line 9: list item index: expected 2, got 3\n"
+        )
+        .unwrap(),
+        "H.4072/H.4072.adoc\n  asciidoctor: WARNING: H.4072.adoc: line 7: unterminated open block\n  asciidoctor: WARNING: H.4072.adoc: line 9: list item index: expected 2, got 3"
+    );
+    assert_eq!(
+        format_asciidoctor_messages(path, b"Rendered\n", b"Warned\n").unwrap(),
+        "H.4072/H.4072.adoc\n  Rendered\n  Warned"
+    );
+}
+
+#[test]
+fn it_skips_unchanged_and_rendered_asciidocs() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let adoc_path = temp_dir.path().join("H.4072.adoc");
+    let html_path = temp_dir.path().join("H.4072.html");
+    let output_folder = temp_dir.path().to_str().unwrap();
+
+    // An identical document is not rewritten
+    assert!(write_if_changed(&adoc_path, b"= H.4072\n").unwrap());
+    assert!(!write_if_changed(&adoc_path, b"= H.4072\n").unwrap());
+    assert!(write_if_changed(&adoc_path, b"= H.4072\n\nChanged\n").unwrap());
+
+    // Rendering is skipped while the HTML is newer, so a failing executable isn't run, unless
+    // forced
+    fs::write(&html_path, "").unwrap();
+    let adoc_modified = fs::metadata(&adoc_path).unwrap().modified().unwrap();
+    let html_file = File::options().write(true).open(&html_path).unwrap();
+    html_file
+        .set_modified(adoc_modified + Duration::from_secs(1))
+        .unwrap();
+    assert!(is_rendered(&adoc_path, &html_path));
+    run_asciidoctor(output_folder, "false", false).expect("Could not skip rendered asciidoc");
+    assert!(run_asciidoctor(output_folder, "false", true).is_err());
+
+    // A newer document is rendered again
+    html_file
+        .set_modified(adoc_modified - Duration::from_secs(1))
+        .unwrap();
+    assert!(!is_rendered(&adoc_path, &html_path));
+    assert!(run_asciidoctor(output_folder, "false", false).is_err());
+    assert!(Cli::try_parse_from(["springbok-mgl", "--force", "--no-asciidoctor"]).is_err());
+}
+
+#[test]
+fn it_creates_no_law_sections_text_for_h4072() {
+    let file = File::open("./tests/test-data/H.4072.txt").expect("no such file");
+    let text_nodes: Vec<String> = BufReader::new(file)
+        .lines()
+        .map(|l| l.expect("Could not parse line"))
+        .collect();
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
+    let config = Config::default();
+    let (law_sections_text, law_section_failures) =
+        create_law_sections_text(&config, &bill, 4, false, None, false);
+    assert!(law_sections_text.is_empty());
+    assert!(law_section_failures.is_empty());
+}
+
+#[test]
+fn it_reads_fresh_cache_files() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let mut cache = Cache {
+        cache_dir: temp_dir.path().join("cache"),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    assert_eq!(read_cache(&cache, "40-7.txt"), None);
+    write_cache(&cache, "40-7.txt", "Section 7. Law text");
+    assert_eq!(
+        read_cache(&cache, "40-7.txt"),
+        Some(String::from("Section 7. Law text"))
+    );
+
+    // Refreshing skips the cache
+    cache.refresh = true;
+    assert_eq!(read_cache(&cache, "40-7.txt"), None);
+
+    // Stale cache files are skipped
+    cache.refresh = false;
+    cache.ttl = Duration::ZERO;
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(read_cache(&cache, "40-7.txt"), None);
+}
+
+#[test]
+fn it_writes_section_counts_csv() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let csv_path = temp_dir.path().join("counts.csv");
+    let bills_section_counts = vec![
+        (
+            String::from("H.4072"),
+            SectionCounts {
+                total: 3,
+                amending: 0,
+                amending_by_striking_and_inserting: 0,
+                amending_by_striking: 0,
+                amending_by_inserting: 0,
+                repealing: 0,
+                effective_date: 1,
+                appropriation: 0,
+                other: 2,
+            },
+        ),
+        (
+            String::from("S.2482"),
+            SectionCounts {
+                total: 4,
+                amending: 2,
+                amending_by_striking_and_inserting: 0,
+                amending_by_striking: 1,
+                amending_by_inserting: 1,
+                repealing: 0,
+                effective_date: 0,
+                appropriation: 0,
+                other: 2,
+            },
+        ),
+    ];
+    write_section_counts_csv(&bills_section_counts, &csv_path).expect("Could not write CSV");
+
+    let csv = fs::read_to_string(&csv_path).expect("Could not read CSV");
+    let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+    assert_eq!(
+        rows[0],
+        vec![
+            "bill_number",
+            "total",
+            "amending",
+            "amending_by_striking_and_inserting",
+            "amending_by_striking",
+            "amending_by_inserting",
+            "repealing",
+            "effective_date",
+            "appropriation",
+            "other"
+        ]
+    );
+    assert_eq!(
+        rows[1],
+        vec!["H.4072", "3", "0", "0", "0", "0", "0", "1", "0", "2"]
+    );
+    assert_eq!(
+        rows[2],
+        vec!["S.2482", "4", "2", "0", "1", "1", "0", "0", "0", "2"]
+    );
+    assert_eq!(rows.len(), 3);
+}
+
+#[test]
+fn it_creates_bill_from_html() {
+    let html = fs::read_to_string("./tests/test-data/H.4072.html").expect("no such file");
+    let text_nodes = get_bill_text_nodes_from_html(&html).expect("Could not get text nodes");
+    let file = File::open("./tests/test-data/H.4072.txt").expect("no such file");
+    let expected_text_nodes: Vec<String> = BufReader::new(file)
+        .lines()
+        .map(|l| l.expect("Could not parse line"))
+        .collect();
+    assert_eq!(text_nodes, expected_text_nodes);
+
+    let (bill, section_counts) = create_bill_from_html(&html).expect("Could not create bill");
+    assert_eq!(bill.bill_sections.len(), 3);
+    assert_eq!(section_counts.total, 3);
+    assert_eq!(section_counts.effective_date, 1);
+    assert_eq!(section_counts.other, 2);
+}
+
+#[test]
+fn it_errs_on_html_without_bill_text() {
+    assert!(get_bill_text_nodes_from_html("<html><body></body></html>").is_err());
+}
+
+#[test]
+fn it_gets_bill_text_href_with_fallback_selectors() {
+    let html = r#"<html><body><div class="modalBtnGroup"><a href="/Bills/193/H4072.pdf">PDF</a></div></body></html>"#;
+    assert_eq!(
+        get_bill_text_href(html).expect("Could not get bill text link"),
+        "/Bills/193/H4072.pdf"
+    );
+
+    let html = r#"<html><body><a href="/Bills/193/H4072/BillText">Bill Text</a></body></html>"#;
+    assert_eq!(
+        get_bill_text_href(html).expect("Could not get bill text link"),
+        "/Bills/193/H4072/BillText"
+    );
+
+    let error = get_bill_text_href("<html><body></body></html>").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("div.modalBtnGroup a:nth-child(1)"));
+}
+
+#[test]
+fn it_gets_bill_text_nodes_from_pdf_text() {
+    let pdf_text = "SECTION 1. Chapter 40 of the General Laws is hereby\namended by striking out section 7.\n\nSECTION 2. This act shall take effect\n  upon its passage.\n";
+    assert_eq!(
+        get_bill_text_nodes_from_pdf_text(pdf_text),
+        vec![
+            "SECTION 1. Chapter 40 of the General Laws is hereby amended by striking out section 7.",
+            "SECTION 2. This act shall take effect upon its passage.",
+        ]
+    );
+}
+
+#[test]
+fn it_detects_bill_text_pdf_links() {
+    let html = r#"<html><body><a href="/Bills/193/H4072.PDF">Download</a></body></html>"#;
+    let text_href = get_bill_text_href(html).expect("Could not get bill text link");
+    let text_url = Config::default().base_url.join(&text_href).unwrap();
+    assert!(is_pdf_url(&text_url));
+    assert!(!is_pdf_url(
+        &Config::default()
+            .base_url
+            .join("/Bills/193/H4072/BillText")
+            .unwrap()
+    ));
+}
+
+#[cfg(not(feature = "pdf"))]
+#[test]
+fn it_errs_on_pdf_bill_text_without_pdf_feature() {
+    use springbok_mgl::{get_bill_text_nodes, HttpClient};
+    use std::{error::Error, sync::Arc};
+    use url::Url;
+
+    #[derive(Debug)]
+    struct PdfClient;
+
+    impl HttpClient for PdfClient {
+        fn get(&self, _url: &Url) -> Result<String, Box<dyn Error>> {
+            Ok("%PDF-1.4".to_string())
+        }
+    }
+
+    let config = Config {
+        client: Arc::new(PdfClient),
+        ..Config::default()
+    };
+    let bill_url = config.base_url.join("/Bills/193/H4072").unwrap();
+    let html = r#"<html><body><a href="/Bills/193/H4072.pdf">Download</a></body></html>"#;
+    let error = get_bill_text_nodes(&config, &bill_url, html).unwrap_err();
+    assert!(error.to_string().contains("\"pdf\" feature"));
+    assert!(error.to_string().contains("H4072.pdf"));
+}
+
+#[test]
+fn it_parses_bill_metadata() {
+    let search_entry = SearchEntry {
+        bill_url: url::Url::parse("https://malegislature.gov/Bills/193/H4072").unwrap(),
+        bill_sponsor: String::from("Search Sponsor"),
+        bill_summary: String::from("Search summary"),
+        doc_type: DocType::Bill,
+        filed_date: None,
+    };
+    let html = r#"<html><body>
+        <h1>Bill H.4072</h1>
+        <h2>An Act relative to town clerks</h2>
+        <dl><dt>Sponsor:</dt><dd><a href="/Legislators/Profile/ABC">Jane Doe</a></dd></dl>
+        </body></html>"#;
+    let bill = parse_bill_metadata(html, "H.4072", &search_entry);
+    assert_eq!(bill.bill_number, "H.4072");
+    assert_eq!(bill.title, "An Act relative to town clerks");
+    assert_eq!(bill.sponsor, "Jane Doe");
+    assert_eq!(bill.general_court, "193");
+    assert!(bill.bill_sections.is_empty());
+
+    // Fall back to the search entry when the page lacks metadata
+    let bill = parse_bill_metadata("<html><body></body></html>", "H.4072", &search_entry);
+    assert_eq!(bill.title, "Search summary");
+    assert_eq!(bill.sponsor, "Search Sponsor");
+}
+
+#[test]
+fn it_collects_lettered_bill_sections() {
+    let file = File::open("./tests/test-data/lettered-sections.txt").expect("no such file");
+    let text_nodes: Vec<String> = BufReader::new(file)
+        .lines()
+        .map(|l| l.expect("Could not parse line"))
+        .collect();
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
+    let section_numbers: Vec<&str> = bill
+        .iter()
+        .map(|bill_section| bill_section.section_number.as_str())
+        .collect();
+
+    // The unnumbered section is kept separate, rather than merged into section 2
+    assert_eq!(section_numbers, vec!["1", "1A", "2", "", "2B", "3"]);
+    assert!(bill[1]
+        .text
+        .ends_with("Section 5A. The secretary shall keep records."));
+}
+
+#[test]
+fn it_collects_line_refs_from_numbered_bill_sections() {
+    let file = File::open("./tests/test-data/numbered-lines.txt").expect("no such file");
+    let text_nodes: Vec<String> = BufReader::new(file)
+        .lines()
+        .map(|l| l.expect("Could not parse line"))
+        .collect();
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
+    assert_eq!(bill.len(), 2);
+
+    // Line numbers are removed, and printed lines rejoined
+    assert_eq!(
+        bill[0].text,
+        "SECTION 1. Section 7 of chapter 40 of the General Laws reads in part: The town shall elect a clerk for a term of three years. Said section 7 is hereby amended by striking out lines 2 and 3 and inserting in place thereof the following:- The town shall appoint a clerk."
+    );
+    assert_eq!(bill[0].law_sections.section_numbers, vec!["7"]);
+    let line_refs = bill[0].line_refs.as_ref().expect("No line refs");
+    assert_eq!(line_refs.first_line, 1);
+    assert_eq!(line_refs.lines.len(), 5);
+    assert_eq!(line_refs.get(2), Some("The town shall elect a clerk"));
+    assert_eq!(line_refs.get(6), None);
+
+    // Bills number lines continuously, so the next section starts at the next line
+    assert_eq!(bill[1].section_number, "2");
+    assert_eq!(
+        bill[1].text,
+        "SECTION 2. This act shall take effect upon its passage."
+    );
+    assert_eq!(bill[1].line_refs.as_ref().unwrap().first_line, 6);
+    assert_eq!(bill[1].line_refs.as_ref().unwrap().line_index().len(), 6);
+
+    // Numbers which aren't consecutive aren't line numbers
+    let bill = collect_bill_sections(
+        &[
+            String::from("SECTION 1. There are hereby appropriated"),
+            String::from("100 dollars for clerks, and"),
+            String::from("300 dollars for records."),
+        ],
+        &section_regex,
+    );
+    assert!(bill[0].line_refs.is_none());
+    assert!(bill[0].text.contains("\n100 dollars"));
+}
+
+#[test]
+fn it_collects_bill_sections_with_split_section_numbers() {
+    let file = File::open("./tests/test-data/split-section-numbers.txt").expect("no such file");
+    let text_nodes: Vec<String> = BufReader::new(file)
+        .lines()
+        .map(|l| l.expect("Could not parse line"))
+        .collect();
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
+    let section_numbers: Vec<&str> = bill
+        .iter()
+        .map(|bill_section| bill_section.section_number.as_str())
+        .collect();
+
+    // The period after each section number is rejoined with it, so the law is still found
+    assert_eq!(section_numbers, vec!["1", "12", "13"]);
+    assert!(bill[0]
+        .text
+        .starts_with("SECTION 1.\nSection 5 of chapter 41"));
+    assert!(bill[1]
+        .text
+        .starts_with("SECTION 12. Section 7 of chapter 40"));
+    assert_eq!(bill[0].law_sections.chapter_number, "41");
+    assert_eq!(bill[0].law_sections.section_numbers, vec!["5"]);
+    assert_eq!(bill[1].law_sections.chapter_number, "40");
+    assert_eq!(bill[1].law_sections.section_numbers, vec!["7"]);
+}
+
+#[test]
+fn it_drops_whitespace_only_text_nodes() {
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby amended by striking out subsection (a) and inserting in place thereof the following subsection:-   "),
+        String::from(""),
+        String::from("  \t "),
+        String::from("(a) The town shall elect a clerk.\n\n\n\nThe clerk shall keep records."),
+        String::from("\n"),
+        String::from("SECTION 2. This act shall take effect upon its passage."),
+        String::from("   "),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    assert_eq!(bill.len(), 2);
+    assert_eq!(
+        bill[0].text,
+        "SECTION 1. Section 7 of chapter 40 of the General Laws is hereby amended by striking out subsection (a) and inserting in place thereof the following subsection:-\n\
+         (a) The town shall elect a clerk.\n\n\
+         The clerk shall keep records."
+    );
+    assert_eq!(
+        bill[1].text,
+        "SECTION 2. This act shall take effect upon its passage."
+    );
+}
+
+#[test]
+fn it_collects_bill_preamble_apart_from_sections() {
+    let text_nodes = vec![
+        String::from("Whereas, The deferred operation of this act would tend to defeat its purpose, which is to provide forthwith for the election of town clerks, therefore it is hereby declared to be an emergency law, necessary for the immediate preservation of the public convenience."),
+        String::from("Be it enacted by the Senate and House of Representatives in General Court assembled, and by the authority of the same, as follows:"),
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. This act shall take effect upon its passage."),
+    ];
+    let (bill, section_counts) = create_bill_from_text_nodes(&text_nodes);
+    let preamble = bill.preamble.expect("Could not collect preamble");
+    assert!(preamble.starts_with("Whereas, The deferred operation"));
+    assert!(preamble.ends_with("as follows:"));
+    assert_eq!(bill.bill_sections.len(), 2);
+    assert_eq!(bill.bill_sections[0].section_number, "1");
+    assert!(bill.bill_sections[0]
+        .text
+        .starts_with("SECTION 1. Section 7"));
+    assert_eq!(section_counts.total, 2);
+
+    // A bill without sections is a single section, not a preamble
+    let text_nodes = vec![String::from(
+        "Section 7 of chapter 40 of the General Laws is hereby repealed.",
+    )];
+    let (bill, _) = create_bill_from_text_nodes(&text_nodes);
+    assert_eq!(bill.preamble, None);
+    assert_eq!(bill.bill_sections.len(), 1);
+}
+
+#[test]
+fn it_gets_log_level_from_quiet_and_verbose() {
+    let get_level = |args: &[&str]| {
+        let cli = Cli::parse_from([&["springbok-mgl"], args].concat());
+        get_log_level(&cli)
+    };
+    assert_eq!(get_level(&[]), LevelFilter::Warn);
+    assert_eq!(get_level(&["-q"]), LevelFilter::Error);
+    assert_eq!(get_level(&["-v"]), LevelFilter::Info);
+    assert_eq!(get_level(&["-vv"]), LevelFilter::Debug);
+    assert_eq!(get_level(&["-vvv"]), LevelFilter::Trace);
+    assert!(Cli::try_parse_from(["springbok-mgl", "-q", "-v"]).is_err());
+}
+
+#[test]
+fn it_creates_law_sections_text_sequentially() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().to_path_buf(),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "40-7.txt",
+        "Section 7. The town shall elect a clerk.",
+    );
+    write_cache(
+        &cache,
+        "41-5.txt",
+        "Section 5. The clerk shall keep records.",
+    );
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. Section 5 of chapter 41 of the General Laws is hereby repealed."),
+        String::from("SECTION 3. Section 6 of chapter 41 of the General Laws is hereby repealed."),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+
+    // Cached sections are used, and the uncached section fails at an unreachable base URL
+    let config = Config {
+        base_url: url::Url::parse("http://127.0.0.1:9").unwrap(),
+        retries: 0,
+        ..Config::default()
+    };
+    let (law_sections_text, law_section_failures) =
+        create_law_sections_text(&config, &bill, 4, true, Some(cache), false);
+    assert_eq!(law_sections_text.len(), 2);
+    assert_eq!(law_section_failures.len(), 1);
+    assert_eq!(law_section_failures[0].law_chapter, "41");
+    assert_eq!(law_section_failures[0].law_section, "6");
+}
+
+#[test]
+fn it_stops_downloading_law_sections_at_deadline() {
+    // Accept connections but never respond, so only the deadline ends the downloads
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind listener");
+    let base_url = url::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let _handle = thread::spawn(move || {
+        let _streams: Vec<_> = listener.incoming().collect();
+    });
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. Section 5 of chapter 41 of the General Laws is hereby repealed."),
+        String::from("SECTION 3. Section 6 of chapter 41 of the General Laws is hereby repealed."),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let config = Config {
+        base_url,
+        retries: 0,
+        deadline: Some(Instant::now() + Duration::from_millis(200)),
+        ..Config::default()
+    };
+
+    // Each law section fails by the deadline, well before the request timeout
+    let start = Instant::now();
+    let (law_sections_text, law_section_failures) =
+        create_law_sections_text(&config, &bill, 2, false, None, false);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(law_sections_text.is_empty());
+    assert_eq!(law_section_failures.len(), 3);
+    assert!(law_section_failures
+        .iter()
+        .all(|law_section_failure| law_section_failure.message.contains("Deadline exceeded")));
+}
+
+#[test]
+fn it_stops_download_workers_at_deadline() {
+    // Respond to each request only after the deadline, counting the requests made
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind listener");
+    let base_url = url::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let request_count = Arc::new(AtomicUsize::new(0));
+    let server_request_count = Arc::clone(&request_count);
+    let _handle = thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            server_request_count.fetch_add(1, Ordering::SeqCst);
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer);
+            thread::sleep(Duration::from_millis(200));
+            let _ = stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+        }
+    });
+    let config = Config {
+        base_url,
+        retries: 0,
+        ..Config::default()
+    };
+    let config = Config {
+        deadline: Some(Instant::now() + Duration::from_millis(100)),
+        ..config
+    };
+
+    // The receiver is dropped at the deadline, before the worker finishes its download
+    let (tx, rx) = mpsc::channel();
+    let required_law_sections = vec![
+        (String::from("40"), String::from("7")),
+        (String::from("41"), String::from("5")),
+        (String::from("41"), String::from("6")),
+    ];
+    let handles = download_law_sections(&config, required_law_sections, 1, None, tx);
+    drop(rx);
+
+    // The worker neither panics sending its result, nor starts another download
+    for handle in handles {
+        assert!(handle.join().is_ok());
+    }
+    assert_eq!(request_count.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn it_creates_law_sections_text_asynchronously() {
+    use springbok_mgl::{build_async_client, create_law_sections_text_async, DEFAULT_USER_AGENT};
+
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().to_path_buf(),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "40-7.txt",
+        "Section 7. The town shall elect a clerk.",
+    );
+    write_cache(
+        &cache,
+        "41-5.txt",
+        "Section 5. The clerk shall keep records.",
+    );
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. Section 5 of chapter 41 of the General Laws is hereby repealed."),
+        String::from("SECTION 3. Section 6 of chapter 41 of the General Laws is hereby repealed."),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+
+    // Results match the blocking downloader, in sorted order
+    let config = Config {
+        base_url: url::Url::parse("http://127.0.0.1:9").unwrap(),
+        retries: 0,
+        ..Config::default()
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let (law_sections_text, law_section_failures) = runtime.block_on(async {
+        let client = build_async_client(Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap();
+        create_law_sections_text_async(&config, &client, &bill, 2, Some(cache), false).await
+    });
+    let keys: Vec<&str> = law_sections_text
+        .iter()
+        .map(|law_section_text| law_section_text.law_chapter_key.as_str())
+        .collect();
+    assert_eq!(keys, vec!["40-7", "41-5"]);
+    assert_eq!(law_section_failures.len(), 1);
+    assert_eq!(law_section_failures[0].law_section, "6");
+}
+
+#[test]
+fn it_writes_single_doc_in_bill_order() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().join("cache"),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "40-7.txt",
+        "Section 7.\nThe town shall elect a clerk.",
+    );
+    write_cache(
+        &cache,
+        "41-5.txt",
+        "Section 5.\nThe clerk shall keep records.",
+    );
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 5 of chapter 41 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. Section 7 of chapter 40 of the General Laws is hereby repealed."),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let (law_sections_text, _) =
+        create_law_sections_text(&Config::default(), &bill, 1, true, Some(cache), false);
+
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    write_asciidocs(
+        law_sections_text,
+        &bill,
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Adoc,
+        true,
+    )
+    .expect("Could not write asciidocs");
+    assert!(!Path::new(output_folder).join(LAW_FOLDER).exists());
+
+    // Law sections follow the bill sections which reference them, not chapter order
+    let document = fs::read_to_string(Path::new(output_folder).join("H.4072.adoc")).unwrap();
+    let chapter_41 = document.find("== Chapter 41, Section 5").unwrap();
+    let chapter_40 = document.find("== Chapter 40, Section 7").unwrap();
+    assert!(chapter_41 < chapter_40);
+}
+
+#[test]
+fn it_writes_html_without_asciidoctor() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().join("cache"),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "41-5.txt",
+        "Section 5.\nThe clerk shall keep <all> records.",
+    );
+    let text_nodes = vec![String::from(
+        "SECTION 1. Section 5 of chapter 41 of the General Laws is hereby repealed.",
+    )];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let (law_sections_text, _) =
+        create_law_sections_text(&Config::default(), &bill, 1, true, Some(cache), false);
+
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    write_asciidocs(
+        law_sections_text,
+        &bill,
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Html,
+        false,
+    )
+    .expect("Could not write html");
+    let law_document =
+        fs::read_to_string(Path::new(output_folder).join(LAW_FOLDER).join("41-5.html")).unwrap();
+    assert!(law_document.starts_with("<!DOCTYPE html>"));
+    assert!(law_document.contains(
+        "<del class=\"red\">The clerk shall keep &lt;all&gt; records.</del><sup>1</sup>"
+    ));
+    let document = fs::read_to_string(Path::new(output_folder).join("H.4072.html")).unwrap();
+    assert!(document.contains("<h2>Chapter 41</h2>"));
+    assert!(document.contains("<h3>Section 5.</h3>"));
+}
+
+#[test]
+fn it_writes_unified_diffs() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().join("cache"),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "41-5.txt",
+        "Section 5.\nThe clerk shall keep records.\n\nThe clerk shall publish records.",
+    );
+    let text_nodes = vec![String::from(
+        "SECTION 1. Section 5 of chapter 41 of the General Laws is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:- post.",
+    )];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let (law_sections_text, _) =
+        create_law_sections_text(&Config::default(), &bill, 1, true, Some(cache), false);
+
+    let output_folder = temp_dir.path().join("H.4072");
+    let output_folder = output_folder.to_str().unwrap();
+    write_asciidocs(
+        law_sections_text,
+        &bill,
+        output_folder,
+        &FileNaming::default(),
+        &MarkupStyle::default(),
+        MarkupFormat::Diff,
+        false,
+    )
+    .expect("Could not write diffs");
+    let law_diff =
+        fs::read_to_string(Path::new(output_folder).join(LAW_FOLDER).join("41-5.diff")).unwrap();
+    assert_eq!(
+        law_diff,
+        "--- a/41-5\n+++ b/41-5\n@@ -2,4 +2,4 @@\n \n The clerk shall keep records.\n \n-The clerk shall publish records.\n+The clerk shall post records.\n"
+    );
+    let diff = fs::read_to_string(Path::new(output_folder).join("H.4072.diff")).unwrap();
+    assert_eq!(diff, law_diff);
+}
+
+#[test]
+fn it_downloads_each_law_section_once() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().to_path_buf(),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    write_cache(
+        &cache,
+        "40-6A.txt",
+        "Section 6A. The town may appoint a clerk.",
+    );
+    let bill: Vec<BillSection> = [("1", "6A"), ("2", " 6a "), ("3", "6a")]
+        .iter()
+        .map(|(section_number, law_section)| BillSection {
+            section_number: section_number.to_string(),
+            text: String::new(),
+            law_sections: LawSections {
+                chapter_number: String::from("40"),
+                section_numbers: vec![law_section.to_string()],
+                law_source: LawSource::GeneralLaws,
+            },
+            unresolved: false,
+            line_refs: None,
+        })
+        .collect();
+
+    // Only the cached spelling can be read, since the base URL is unreachable
+    let config = Config {
+        base_url: url::Url::parse("http://127.0.0.1:9").unwrap(),
+        retries: 0,
+        ..Config::default()
+    };
+    let (law_sections_text, law_section_failures) =
+        create_law_sections_text(&config, &bill, 4, true, Some(cache), false);
+    assert!(law_section_failures.is_empty());
+    assert_eq!(law_sections_text.len(), 1);
+    assert_eq!(law_sections_text[0].law_chapter_key, "40-6A");
+    assert_eq!(law_sections_text[0].bill_section_keys, vec!["1", "2", "3"]);
+}
+
+#[test]
+fn it_flags_unresolved_law_references() {
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 5 of chapter 41 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. The General Laws are hereby amended by striking out the words \"town clerk\"."),
+        String::from("SECTION 3. This act shall take effect upon its passage."),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let unresolved: Vec<&str> = bill
+        .iter()
+        .filter(|bill_section| bill_section.unresolved)
+        .map(|bill_section| bill_section.section_number.as_str())
+        .collect();
+    assert_eq!(unresolved, vec!["2"]);
+}
+
+#[test]
+fn it_gets_required_law_sections_without_downloading() {
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby repealed."),
+        String::from("SECTION 2. Section 5 of chapter 41 of the General Laws is hereby repealed."),
+        String::from("SECTION 3. Section 7 of chapter 40 of the General Laws, as appearing in the 2020 Official Edition, is hereby amended by striking out the word \"clerk\"."),
+    ];
+    let bill = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let (required_law_sections, law_section_bill_sections) = get_required_law_sections(&bill);
+    assert_eq!(
+        required_law_sections,
+        vec![
+            (String::from("40"), String::from("7")),
+            (String::from("41"), String::from("5"))
+        ]
+    );
+    assert_eq!(law_section_bill_sections["40-7"], vec!["1", "3"]);
+    assert_eq!(law_section_bill_sections["41-5"], vec!["2"]);
+    assert!(Cli::parse_from(["springbok-mgl", "--dry-run"]).dry_run);
+    assert!(Cli::parse_from(["springbok-mgl", "--counts-only"]).counts_only);
+    assert!(Cli::try_parse_from(["springbok-mgl", "--counts-only", "--dry-run"]).is_err());
+}
+
+#[test]
+fn it_gets_output_folder() {
+    let cli = Cli::parse_from(["springbok-mgl", "-s", "H.4072"]);
+    assert_eq!(get_output_folder(&cli, "H.4072"), "H.4072");
+    assert_eq!(get_output_folder(&cli, "H.4072/../S:1 "), "H.4072_.._S_1");
+    assert_eq!(sanitize_folder_name(" .. "), "bill");
+
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "-s",
+        "H.4072",
+        "--output-dir",
+        "bills/H4072",
+    ]);
+    assert_eq!(get_output_folder(&cli, "H.4072"), "bills/H4072");
+}
+
+#[test]
+fn it_names_types_by_canonical_paths() {
+    use springbok_mgl::prelude::*;
+
+    let text_nodes = vec![String::from(
+        "SECTION 1. Section 7 of chapter 40 of the General Laws is hereby repealed.",
+    )];
+    let bill: Vec<springbok_mgl::bill_section::BillSection> =
+        springbok_mgl::bill_section::collect_bill_sections(
+            &text_nodes,
+            &springbok_mgl::bill_section::init_bill_section_regex(),
+        );
+    let law_sections: &LawSections = &bill[0].law_sections;
+    assert_eq!(law_sections.chapter_number, "40");
+    let _: &BillSection = &bill[0];
+    assert_eq!(MarkupFormat::default(), springbok_mgl::MarkupFormat::Adoc);
+}
+
+#[test]
+fn it_dumps_bill_sections() {
+    let text_nodes = vec![
+        String::from("SECTION 1. Section 7 of chapter 40 of the General Laws is hereby amended by striking out the word \"town\" and inserting in place thereof the following word:- \"city\"."),
+        String::from("SECTION 2. Sections 5 and 6 of chapter 41 of the General Laws are hereby repealed."),
+        String::from("SECTION 3. This act shall take effect upon its passage."),
+    ];
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(&text_nodes, &section_regex);
+    assert_eq!(
+        springbok_mgl::format_bill_sections(&bill, &section_regex),
+        "SECTION 1 — amending_by_striking_and_inserting — chapter 40, sections 7\n\
+         SECTION 2 — repealing — chapter 41, sections 5, 6\n\
+         SECTION 3 — effective_date — no law sections\n"
+    );
+}
+
+#[test]
+fn it_creates_bill_from_text_nodes_on_stdin() {
+    let text = fs::read_to_string("./tests/test-data/H.4072.txt").unwrap();
+    let text_nodes = read_text_nodes(io::Cursor::new(text)).expect("Could not read text nodes");
+    let (bill, section_counts) = create_bill_from_text_nodes(&text_nodes);
+    assert_eq!(bill.bill_sections.len(), 3);
+    assert_eq!(bill.preamble, None);
+    assert_eq!(section_counts.effective_date, 1);
+
+    // Reading stdin replaces downloading a bill
+    assert!(
+        Cli::try_parse_from(["springbok-mgl", "--stdin"])
+            .unwrap()
+            .stdin
+    );
+    assert!(Cli::try_parse_from(["springbok-mgl", "--stdin", "--bill", "H.4072"]).is_err());
+}
+
+#[test]
+fn it_gets_bill_numbers_from_options_and_file() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let bill_list = temp_dir.path().join("bills.txt");
+    fs::write(&bill_list, "H.4072\n\n  S.2482  \nH.47\n").unwrap();
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--bill",
+        "H.47,H.4220",
+        "--bill",
+        "H.4607",
+        "--bill-list",
+        bill_list.to_str().unwrap(),
+    ]);
+
+    // Bills are kept in the order given, once each
+    assert_eq!(
+        get_bill_numbers(&cli).unwrap(),
+        vec!["H.47", "H.4220", "H.4607", "H.4072", "S.2482"]
+    );
+    let cli = Cli::parse_from(["springbok-mgl", "--bill-list", "no-such-file.txt"]);
+    assert!(get_bill_numbers(&cli).is_err());
+}
+
+#[test]
+fn it_formats_section_counts_summary() {
+    let section_counts = SectionCounts {
+        total: 4,
+        amending: 1,
+        amending_by_striking_and_inserting: 1,
+        amending_by_striking: 0,
+        amending_by_inserting: 0,
+        repealing: 0,
+        effective_date: 0,
+        appropriation: 2,
+        other: 1,
+    };
+    assert_eq!(
+        format_section_counts_summary(&[(String::from("H.47"), section_counts)]),
+        "Bill  Total  Amending  Repealing  Effective date  Appropriation  Other\n\
+         H.47      4         1          0               0              2      1\n"
+    );
+}
@@ -0,0 +1,455 @@
+use clap::Parser;
+use springbok_mgl::{
+    create_bill, create_refiner_listing, create_refiner_map, filter_search_results_by_date,
+    format_refiner_cache, format_refiner_table, get_bill_url, get_page_url, get_search_results,
+    parse_filing_date, parse_page_count, parse_refiner_map, parse_search_results, parse_table_date,
+    print_entries_or_append_query_pairs, write_cache, Cache, Cli, Config, DocType, HttpClient,
+    SpringbokError,
+};
+use std::{error::Error, fs, path::Path, sync::Arc, time::Duration};
+use url::Url;
+
+#[derive(Debug)]
+struct FixtureClient;
+
+impl HttpClient for FixtureClient {
+    fn get(&self, url: &Url) -> Result<String, Box<dyn Error>> {
+        // Serve the saved search page for any search, without a server
+        match url.path() {
+            "/Bills/Search" => Ok(fs::read_to_string(
+                Path::new("./tests/test-data/site").join("search.html"),
+            )?),
+            path => Err(format!("No fixture for {path}").into()),
+        }
+    }
+}
+
+#[test]
+fn it_searches_with_a_stub_client() {
+    let config = Config {
+        client: Arc::new(FixtureClient),
+        ..Config::default()
+    };
+    let refiner_map = create_refiner_map(&config, None).expect("Could not create refiners");
+    assert!(refiner_map["Branch"].contains_key("House"));
+
+    let search_url = config
+        .base_url
+        .join("/Bills/Search?SearchTerms=H.4072")
+        .unwrap();
+    let search_results =
+        get_search_results(&config, &search_url, 1).expect("Could not get search results");
+    assert_eq!(search_results["H.4072"].bill_sponsor, "Jane Doe");
+
+    // Stub errors are not retried
+    let bill_url = config.base_url.join("/Bills/193/H4072").unwrap();
+    assert!(springbok_mgl::fetch(&config, &bill_url).is_err());
+}
+
+#[test]
+fn it_lists_refiners_for_a_general_court() {
+    let config = Config {
+        client: Arc::new(FixtureClient),
+        ..Config::default()
+    };
+    let cli = Cli::parse_from(["springbok-mgl", "--list-committees"]);
+    let refiner_listing = create_refiner_listing(&cli, &config)
+        .expect("Could not list refiners")
+        .expect("Expected a refiner listing");
+    assert!(refiner_listing.starts_with("Key"));
+    assert!(refiner_listing.contains("Joint-Rules  Joint Rules (1)"));
+
+    // Each group is headed when listing several
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "--list-legislators",
+        "--list-document-types",
+        "--general-court",
+        "192nd",
+    ]);
+    let refiner_listing = create_refiner_listing(&cli, &config).unwrap().unwrap();
+    assert!(refiner_listing.contains("Sponsor — Legislator (192nd)\n\n"));
+    assert!(refiner_listing.contains("Doe  Doe, Jane"));
+    assert!(refiner_listing.contains("Document Type (192nd)\n\n"));
+
+    // An unknown court lists the known courts
+    let cli = Cli::parse_from(["springbok-mgl", "--list-committees", "-C", "150th"]);
+    let error = create_refiner_listing(&cli, &config).unwrap_err();
+    assert!(error.to_string().contains("193rd"));
+
+    // Nothing is listed unless asked for
+    let cli = Cli::parse_from(["springbok-mgl"]);
+    assert!(create_refiner_listing(&cli, &config).unwrap().is_none());
+}
+
+#[test]
+fn it_errs_on_malformed_search_results() {
+    let body = "<html><body><table><tbody><tr><td>1</td></tr></tbody></table></body></html>";
+    let base_url = Url::parse("https://malegislature.gov").unwrap();
+    let search_results = parse_search_results(body, &base_url);
+    assert!(
+        search_results.is_err(),
+        "Expected an error for a search results row without bill cells"
+    );
+}
+
+#[test]
+fn it_uses_base_url_for_search_result_links() {
+    let body = r#"<html><body><table><tbody><tr>
+        <td></td>
+        <td><a href="/Bills/193/H4072">H.4072</a></td>
+        <td>Jane Doe</td>
+        <td>An Act relative to testing</td>
+        </tr></tbody></table></body></html>"#;
+    let base_url = Url::parse("http://localhost:8080").unwrap();
+    let search_results = parse_search_results(body, &base_url).expect("Could not parse results");
+    let search_entry = search_results.get("H.4072").expect("No search entry");
+    assert_eq!(
+        search_entry.bill_url.as_str(),
+        "http://localhost:8080/Bills/193/H4072"
+    );
+    assert_eq!(search_entry.bill_sponsor, "Jane Doe");
+}
+
+#[test]
+fn it_filters_search_results_by_filing_date() {
+    let body = r#"<html><body><table>
+        <thead><tr><th></th><th>Bill</th><th>Sponsor</th><th>Title</th><th>Date Filed</th></tr></thead>
+        <tbody>
+        <tr>
+        <td></td>
+        <td><a href="/Bills/193/H4072">H.4072</a></td>
+        <td>Jane Doe</td>
+        <td>An Act relative to town clerks</td>
+        <td>1/5/2024</td>
+        </tr>
+        <tr>
+        <td></td>
+        <td><a href="/Bills/193/H1000">H.1000</a></td>
+        <td>John Roe</td>
+        <td>An Act relative to town treasurers</td>
+        <td>12/20/2023</td>
+        </tr>
+        <tr>
+        <td></td>
+        <td><a href="/Bills/193/H2000">H.2000</a></td>
+        <td>John Roe</td>
+        <td>An Act relative to town meetings</td>
+        </tr>
+        </tbody></table></body></html>"#;
+    let base_url = Url::parse("https://malegislature.gov").unwrap();
+    let search_results = parse_search_results(body, &base_url).expect("Could not parse results");
+    assert_eq!(
+        search_results["H.4072"].filed_date.as_deref(),
+        Some("2024-01-05")
+    );
+    assert_eq!(search_results["H.2000"].filed_date, None);
+
+    // Results without a filing date are kept, with a warning, since they cannot be filtered
+    let search_results = filter_search_results_by_date(search_results, Some("2024-01-01"), None);
+    let bill_numbers: Vec<&String> = search_results.keys().collect();
+    assert_eq!(bill_numbers, vec!["H.4072", "H.2000"]);
+    let search_results = filter_search_results_by_date(search_results, None, Some("2023-12-31"));
+    let bill_numbers: Vec<&String> = search_results.keys().collect();
+    assert_eq!(bill_numbers, vec!["H.2000"]);
+
+    assert_eq!(
+        parse_table_date("2024-01-05").as_deref(),
+        Some("2024-01-05")
+    );
+    assert_eq!(parse_table_date("Jan 5, 2024"), None);
+
+    // Without a filing date column, no filing date is read from a fifth cell
+    let body = body.replace("Date Filed", "Status");
+    let search_results = parse_search_results(&body, &base_url).expect("Could not parse results");
+    assert!(search_results
+        .values()
+        .all(|search_entry| search_entry.filed_date.is_none()));
+
+    let cli = Cli::parse_from(["springbok-mgl", "--list", "--since", "2024-01-01"]);
+    assert_eq!(cli.filed_after.as_deref(), Some("2024-01-01"));
+}
+
+#[test]
+fn it_classifies_amendment_search_results() {
+    let body = r#"<html><body><table><tbody>
+        <tr>
+        <td></td>
+        <td><a href="/Bills/193/H4800">H.4800</a></td>
+        <td>Jane Doe</td>
+        <td>An Act making appropriations</td>
+        </tr>
+        <tr>
+        <td></td>
+        <td><a href="/Bills/193/H4800/Amendments/House/12">H.4800 Amendment 12</a></td>
+        <td>John Roe</td>
+        <td>Relative to local aid</td>
+        </tr>
+        </tbody></table></body></html>"#;
+    let base_url = Url::parse("https://malegislature.gov").unwrap();
+    let search_results = parse_search_results(body, &base_url).expect("Could not parse results");
+    assert_eq!(search_results["H.4800"].doc_type, DocType::Bill);
+    let amendment_entry = &search_results["H.4800 Amendment 12"];
+    assert_eq!(amendment_entry.doc_type, DocType::Amendment);
+
+    // Amendments are flagged, rather than failing on the bill text selectors
+    let config = Config::default();
+    let error = create_bill(&config, "H.4800 Amendment 12", amendment_entry).unwrap_err();
+    assert!(error.to_string().contains("is an amendment"));
+}
+
+#[test]
+fn it_parses_empty_search_results() {
+    let body =
+        r#"<html><body><div class="alert">No bills matched your search.</div></body></html>"#;
+    let base_url = Url::parse("https://malegislature.gov").unwrap();
+    let search_results =
+        parse_search_results(body, &base_url).expect("Could not parse empty search results");
+    assert!(search_results.is_empty());
+}
+
+#[test]
+fn it_errs_on_missing_refiners() {
+    let body = "<html><body><div id=\"results\"></div></body></html>";
+    match parse_refiner_map(body) {
+        Err(SpringbokError::Selector { element, .. }) => assert_eq!(element, "refiners"),
+        result => panic!("Expected an error for a search page without refiners, got {result:?}"),
+    }
+}
+
+#[test]
+fn it_parses_page_count() {
+    let body = r#"<html><body><ul class="pagination">
+        <li><a href="?Page=1">1</a></li>
+        <li><a href="?Page=2">2</a></li>
+        <li><a href="?Page=7">7</a></li>
+        <li><a href="?Page=2">Next</a></li>
+        </ul></body></html>"#;
+    assert_eq!(parse_page_count(body), 7);
+    assert_eq!(parse_page_count("<html><body></body></html>"), 1);
+}
+
+#[test]
+fn it_replaces_page_in_url() {
+    let url = Url::parse("https://malegislature.gov/Bills/Search?SearchTerms=mbta&Page=1").unwrap();
+    let page_url = get_page_url(&url, 3);
+    assert_eq!(
+        page_url.as_str(),
+        "https://malegislature.gov/Bills/Search?SearchTerms=mbta&Page=3"
+    );
+}
+
+const REFINERS_BODY: &str = r#"<html><body><div id="refiners">
+    <fieldset><legend>Branch</legend>
+    <label>
+    <input data-refinertoken="486f757365"> House
+    </label>
+    <label>
+    <input data-refinertoken="53656e617465"> Senate
+    </label>
+    </fieldset>
+    </div></body></html>"#;
+
+#[test]
+fn it_parses_refiners() {
+    let refiner_map = parse_refiner_map(REFINERS_BODY).expect("Could not parse refiners");
+    let branch_map = refiner_map.get("Branch").expect("No branch refiners");
+    assert_eq!(branch_map.len(), 2);
+    assert_eq!(branch_map.get("House").unwrap().refiner_token, "486f757365");
+    assert_eq!(branch_map.get("Senate").unwrap().refiner_label, "Senate");
+}
+
+#[test]
+fn it_appends_query_pair_for_each_refiner_value() {
+    let refiner_map = parse_refiner_map(REFINERS_BODY).expect("Could not parse refiners");
+    let branch_map = refiner_map.get("Branch").expect("No branch refiners");
+    let mut search_url = Url::parse("https://malegislature.gov/Bills/Search").unwrap();
+    let do_search = print_entries_or_append_query_pairs(
+        &[String::from("House"), String::from("Senate")],
+        branch_map,
+        "Refinements[lawsbranchname]",
+        &mut search_url,
+        None,
+    )
+    .expect("Could not append query pairs");
+    assert_eq!(do_search, Some(true));
+    let tokens: Vec<_> = search_url
+        .query_pairs()
+        .map(|(_, token)| token.into_owned())
+        .collect();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0], "486f757365");
+
+    // Listing keys appends no query pairs
+    let mut search_url = Url::parse("https://malegislature.gov/Bills/Search").unwrap();
+    let do_search = print_entries_or_append_query_pairs(
+        &[String::from("MISSING")],
+        branch_map,
+        "Refinements[lawsbranchname]",
+        &mut search_url,
+        None,
+    )
+    .expect("Could not list refiners");
+    assert_eq!(do_search, None);
+    assert_eq!(search_url.query(), None);
+
+    // An unknown value is an error which lists the possible values
+    let error = print_entries_or_append_query_pairs(
+        &[String::from("Hosue")],
+        branch_map,
+        "Refinements[lawsbranchname]",
+        &mut search_url,
+        None,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(error.starts_with("Unknown value \"Hosue\" for Refinements[lawsbranchname]"));
+    assert!(error.contains("House   House"));
+    assert!(error.contains("Senate  Senate"));
+    assert_eq!(search_url.query(), None);
+}
+
+#[test]
+fn it_formats_refiner_table() {
+    let refiner_map = parse_refiner_map(REFINERS_BODY).expect("Could not parse refiners");
+    let branch_map = refiner_map.get("Branch").expect("No branch refiners");
+    assert_eq!(
+        format_refiner_table(branch_map, None),
+        "Key     Label\n------  -----\nHouse   House\nSenate  Senate\n"
+    );
+    assert_eq!(
+        format_refiner_table(branch_map, Some("sen")),
+        "Key     Label\n------  -----\nSenate  Senate\n"
+    );
+    assert_eq!(
+        format_refiner_table(branch_map, Some("Governor")),
+        "No values matched\n"
+    );
+}
+
+#[test]
+fn it_parses_multiple_refiner_values() {
+    let cli = Cli::parse_from([
+        "springbok-mgl",
+        "-B",
+        "House,Senate",
+        "-L",
+        "Arciero",
+        "-L",
+        "Ayers",
+    ]);
+    assert_eq!(cli.branch, vec!["House", "Senate"]);
+    assert_eq!(cli.sponsor_legislator, vec!["Arciero", "Ayers"]);
+    assert!(cli.sponsor_committee.is_empty());
+
+    let cli = Cli::parse_from(["springbok-mgl", "-D"]);
+    assert_eq!(cli.document_type, vec!["MISSING"]);
+}
+
+#[test]
+fn it_validates_filing_dates() {
+    assert_eq!(
+        parse_filing_date("2023-01-31"),
+        Ok(String::from("2023-01-31"))
+    );
+    assert!(parse_filing_date("01/31/2023").is_err());
+    assert!(parse_filing_date("2023-13-01").is_err());
+    assert!(parse_filing_date("2023-1-1").is_err());
+    assert!(Cli::try_parse_from(["springbok-mgl", "--filed-after", "yesterday"]).is_err());
+}
+
+#[test]
+fn it_reads_refiners_from_cache() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let cache = Cache {
+        cache_dir: temp_dir.path().to_path_buf(),
+        ttl: Duration::from_secs(60),
+        refresh: false,
+    };
+    let refiner_map = parse_refiner_map(REFINERS_BODY).expect("Could not parse refiners");
+    let refiner_cache = format_refiner_cache(&refiner_map);
+    assert!(refiner_cache
+        .lines()
+        .any(|line| line == "Branch\tHouse\t486f757365"));
+    write_cache(&cache, "refiners.tsv", &refiner_cache);
+    let config = Config::default();
+    let cached_refiner_map =
+        create_refiner_map(&config, Some(&cache)).expect("Could not read refiners");
+    assert_eq!(
+        cached_refiner_map
+            .get("Branch")
+            .unwrap()
+            .get("House")
+            .unwrap()
+            .refiner_token,
+        "486f757365"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn it_serializes_search_entry_url_as_string() {
+    let search_entry = springbok_mgl::SearchEntry {
+        bill_url: Url::parse("https://malegislature.gov/Bills/193/H4072").unwrap(),
+        bill_sponsor: String::from("Mark, Paul W."),
+        bill_summary: String::from("Relative to the town of Greenfield"),
+        doc_type: springbok_mgl::DocType::Bill,
+        filed_date: None,
+    };
+    let search_entry_json = serde_json::to_value(&search_entry).unwrap();
+    assert_eq!(
+        search_entry_json["bill_url"],
+        "https://malegislature.gov/Bills/193/H4072"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn it_lists_search_results_as_json() {
+    let body = r#"<html><body><table><tbody><tr>
+        <td></td>
+        <td><a href="/Bills/193/H4072">H.4072</a></td>
+        <td>Mark, Paul W.</td>
+        <td>Relative to the town of Greenfield</td>
+        </tr></tbody></table></body></html>"#;
+    let base_url = Url::parse("https://malegislature.gov").unwrap();
+    let search_results = parse_search_results(body, &base_url).expect("Could not parse results");
+    let search_results_json = springbok_mgl::get_search_results_json(&search_results);
+    assert_eq!(
+        search_results_json,
+        serde_json::json!([{
+            "bill_number": "H.4072",
+            "general_court": "193",
+            "bill_url": "https://malegislature.gov/Bills/193/H4072",
+            "bill_sponsor": "Mark, Paul W.",
+            "bill_summary": "Relative to the town of Greenfield",
+            "doc_type": "Bill",
+            "filed_date": null,
+        }])
+    );
+
+    // Listing as JSON lists without --list
+    let cli = Cli::parse_from(["springbok-mgl", "--list-json"]);
+    assert!(cli.list_json);
+}
+
+#[test]
+fn it_gets_bill_url_without_searching() {
+    let config = Config::default();
+    assert_eq!(
+        get_bill_url(&config, "193rd", "H.4607").unwrap().as_str(),
+        "https://malegislature.gov/Bills/193/H4607"
+    );
+    assert_eq!(
+        get_bill_url(&config, "192nd", "sd.2897").unwrap().as_str(),
+        "https://malegislature.gov/Bills/192/SD2897"
+    );
+    assert!(get_bill_url(&config, "193rd", "4607").is_err());
+    assert!(get_bill_url(&config, "193rd", "H.46O7").is_err());
+    assert!(get_bill_url(&config, "MISSING", "H.4607").is_err());
+
+    // A bill is downloaded directly, not found by searching
+    let cli = Cli::parse_from(["springbok-mgl", "--bill", "H.4607"]);
+    assert_eq!(cli.bill, vec!["H.4607"]);
+    assert!(Cli::try_parse_from(["springbok-mgl", "--bill", "H.4607", "-s", "mbta"]).is_err());
+}
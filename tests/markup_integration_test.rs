@@ -0,0 +1,61 @@
+use springbok_mgl::{
+    collect_bill_sections, index_law_lines, init_bill_section_regex, init_law_section_regex,
+    init_markup_regex, mark_section_text, LawSectionWithText,
+};
+use std::fs;
+use std::path::Path;
+
+fn mark_fixture(fixture: &str, law_chapter_key: &str) -> String {
+    // Mark up the saved law section text with the bill section text of a fixture
+    let fixture_dir = Path::new("./tests/test-data/markup").join(fixture);
+    let law_text = fs::read_to_string(fixture_dir.join("law.txt")).expect("no such file");
+    let text_nodes: Vec<String> = fs::read_to_string(fixture_dir.join("bill.txt"))
+        .expect("no such file")
+        .lines()
+        .map(String::from)
+        .collect();
+    let bill_sections = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let law_section = LawSectionWithText {
+        law_chapter_key: String::from(law_chapter_key),
+        line_index: Some(index_law_lines(&law_text, &init_law_section_regex())),
+        text: law_text,
+        bill_section_keys: bill_sections
+            .iter()
+            .map(|bill_section| bill_section.section_number.clone())
+            .collect(),
+    };
+    let marked_law_section = mark_section_text(&law_section, &bill_sections, &init_markup_regex())
+        .expect("Could not mark up law section");
+    marked_law_section.text
+}
+
+fn assert_fixture(fixture: &str, law_chapter_key: &str) {
+    let expected_path = Path::new("./tests/test-data/markup")
+        .join(fixture)
+        .join("expected.adoc");
+    let expected_text = fs::read_to_string(expected_path).expect("no such file");
+    let marked_text = mark_fixture(fixture, law_chapter_key);
+    assert_eq!(
+        marked_text.trim_end(),
+        expected_text.trim_end(),
+        "Expected markup for fixture '{}' was:\n{}\nactual result was:\n{}",
+        fixture,
+        expected_text,
+        marked_text
+    );
+}
+
+#[test]
+fn it_marks_repeal() {
+    assert_fixture("repeal", "41-5");
+}
+
+#[test]
+fn it_marks_strike_and_insert_words() {
+    assert_fixture("strike-and-insert-words", "41-5");
+}
+
+#[test]
+fn it_marks_insert_section() {
+    assert_fixture("insert-section", "41-5");
+}
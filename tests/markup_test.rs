@@ -0,0 +1,916 @@
+use springbok_mgl::{
+    apply_amendments, classify_bill_section, collect_bill_sections, collect_law_sections,
+    escape_asciidoc, find_nested_subsection, format_warning_summary, get_paths_with_extension,
+    index_law_lines, init_bill_section_regex, init_law_section_regex, init_markup_regex,
+    init_punctuation_regex, mark_section_text, mark_text, normalize_punctuation,
+    parse_subsection_chain, BillSectionType, LawSectionWithText, MarkupFormat, MarkupStyle,
+    SubsectionChain, Warning, WarningKind,
+};
+use std::fs;
+
+#[test]
+fn it_strikes_whole_section() {
+    let markup_regex = init_markup_regex();
+    let law_section_text =
+        String::from("The town shall elect a clerk.\n\nThe clerk shall serve for three years.");
+    let bill_section_text = String::from(
+        "SECTION 2. Chapter 41 of the General Laws is hereby amended by striking out section 5",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("2"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "[.line-through .red]##The town shall elect a clerk.##\n\n[.line-through .red]##The clerk shall serve for three years.##^2^"
+    );
+}
+
+#[test]
+fn it_strikes_and_inserts_lines_with_line_index() {
+    let markup_regex = init_markup_regex();
+    let law_section_text =
+        String::from("The town shall elect a clerk. The clerk shall serve for three years.");
+    let line_index = index_law_lines(&law_section_text, &init_law_section_regex());
+    assert_eq!(
+        line_index,
+        vec![
+            "The town shall elect a clerk.",
+            "The clerk shall serve for three years."
+        ]
+    );
+    let bill_section_text = String::from(
+        "SECTION 3. Said section 5 of said chapter 41 is hereby amended by striking out line 2 and inserting in place thereof the following:- The clerk shall serve for five years.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("3"),
+        Some(&line_index),
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The town shall elect a clerk. [.line-through .red]##The clerk shall serve for three years.## [.blue]##The clerk shall serve for five years.##^3^"
+    );
+
+    // Without a line index, fall back to a footnote
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("3"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        format!("{}\n\n_{}_", law_section_text, bill_section_text)
+    );
+}
+
+#[test]
+fn it_normalizes_punctuation_of_indexed_lines() {
+    // The law text and its line index are normalized alike, so struck lines still match
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from(
+        "The clerk shall certify as follows:\u{2014} the records are true. The clerk shall serve for three years.",
+    );
+    let line_index = index_law_lines(&law_section_text, &init_law_section_regex());
+    let bill_section_text = String::from(
+        "SECTION 3. Said section 5 of said chapter 41 is hereby amended by striking out line 1 and inserting in place thereof the following:- The clerk shall certify the records.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("3"),
+        Some(&line_index),
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "[.line-through .red]##The clerk shall certify as follows:- the records are true.## [.blue]##The clerk shall certify the records.##^3^ The clerk shall serve for three years."
+    );
+}
+
+#[test]
+fn it_escapes_asciidoc_special_characters() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = escape_asciidoc("* note: the clerk may use C++ to keep records.");
+    assert_eq!(
+        law_section_text,
+        "&#42; note: the clerk may use C&#43;&#43; to keep records."
+    );
+    assert_eq!(
+        escape_asciidoc(".5 percent\n.6 percent"),
+        "&#46;5 percent\n&#46;6 percent"
+    );
+
+    // Markup added for struck and inserted words is not escaped
+    let bill_section_text = escape_asciidoc(
+        "SECTION 4. Said section 5 is hereby amended by striking out the words \"to keep records\" and inserting in place thereof the following words:- to keep *all* records.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("4"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "&#42; note: the clerk may use C&#43;&#43; [.line-through .red]##to keep records## [.blue]##to keep &#42;all&#42; records##^4^."
+    );
+}
+
+#[test]
+fn it_strikes_and_inserts_words_by_occurrence() {
+    let markup_regex = init_markup_regex();
+    let law_section_text =
+        String::from("The clerk shall keep records. The clerk shall publish records.");
+
+    // A single occurrence is replaced
+    let bill_section_text = String::from(
+        "SECTION 5. Said section 5 is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:- post.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("5"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The clerk shall keep records. The clerk shall [.line-through .red]##publish## [.blue]##post##^5^ records."
+    );
+
+    // The occurrence given by the bill section is replaced
+    let bill_section_text = String::from(
+        "SECTION 6. Said section 5 is hereby amended by striking out the word \"shall\", the second time it appears, and inserting in place thereof the following word:- may.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("6"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The clerk shall keep records. The clerk [.line-through .red]##shall## [.blue]##may##^6^ publish records."
+    );
+
+    // The occurrence in the line given by the bill section is replaced
+    let line_index = index_law_lines(&law_section_text, &init_law_section_regex());
+    let bill_section_text = String::from(
+        "SECTION 7. Said section 5 is hereby amended by striking out, in line 1, the word \"records\" and inserting in place thereof the following word:- minutes.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("7"),
+        Some(&line_index),
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The clerk shall keep [.line-through .red]##records## [.blue]##minutes##^7^. The clerk shall publish records."
+    );
+
+    // An ambiguous occurrence falls back to a footnote
+    let bill_section_text = String::from(
+        "SECTION 8. Said section 5 is hereby amended by striking out the word \"clerk\" and inserting in place thereof the following word:- secretary.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("8"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        format!("{}\n\n_{}_", law_section_text, bill_section_text)
+    );
+}
+
+#[test]
+fn it_repeals_section() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The town shall elect a clerk.");
+    let bill_section_text =
+        String::from("SECTION 9. Section 5 of chapter 41 of the General Laws is hereby repealed.");
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("9"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text.trim(),
+        "[.line-through .red]##The town shall elect a clerk.##^9^\n\nREPEALED ."
+    );
+
+    // The law text is empty if the repeal has already gone through
+    let marked_text = mark_text(
+        "",
+        &bill_section_text,
+        &String::from("9"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(marked_text, "REPEALED .^9^");
+}
+
+#[test]
+fn it_repeals_and_replaces_section() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The town shall elect a clerk.");
+    let bill_section_text = String::from(
+        "SECTION 9. Section 5 of chapter 41 of the General Laws is hereby repealed and the following section inserted in place thereof:— Section 5. The town shall appoint a clerk.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("9"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "[.line-through .red]##The town shall elect a clerk.##\n\n[.blue]##Section 5. The town shall appoint a clerk.##^9^"
+    );
+}
+
+#[test]
+fn it_strikes_words() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The clerk shall keep and publish records.");
+    let bill_section_text = String::from(
+        "SECTION 10. Said section 5 is hereby amended by striking out the words \"and publish\".",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("10"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The clerk shall keep [.line-through .red]##and publish##^10^  records."
+    );
+}
+
+#[test]
+fn it_strikes_words_wherever_they_appear() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from(
+        "A motor vehicle and a motor boat shall be registered, unless the motor is removed.",
+    );
+    let bill_section_text = String::from(
+        "SECTION 20. Said section 5 is hereby amended by striking out the word \"motor\", wherever it appears.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("20"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text
+            .matches("[.line-through .red]##motor##^20^")
+            .count(),
+        3
+    );
+    assert!(!marked_text.contains("_SECTION 20."));
+
+    // Only whole words are struck, leaving a longer word which contains them
+    let law_section_text = String::from(
+        "A motor vehicle, other than a motorcycle, shall be registered, unless the motor is removed.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("20"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "A [.line-through .red]##motor##^20^  vehicle, other than a motorcycle, shall be registered, unless the [.line-through .red]##motor##^20^  is removed."
+    );
+    let replacing_text = String::from(
+        "SECTION 22. Said section 5 is hereby amended by striking out the word \"motor\", wherever it appears, and inserting in place thereof the following word:- engine.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &replacing_text,
+        &String::from("22"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "A [.line-through .red]##motor## [.blue]##engine##^22^ vehicle, other than a motorcycle, shall be registered, unless the [.line-through .red]##motor## [.blue]##engine##^22^ is removed."
+    );
+
+    // Without the qualifier, a word which appears more than once is ambiguous
+    let bill_section_text = String::from(
+        "SECTION 21. Said section 5 is hereby amended by striking out the word \"motor\".",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("21"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        format!("{}\n\n_{}_", law_section_text, bill_section_text)
+    );
+}
+
+#[test]
+fn it_adds_section_at_end_of_chapter() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The town shall elect a clerk.");
+    let bill_section_text = String::from(
+        "SECTION 12. Chapter 41 of the General Laws is hereby amended by adding the following section:— Section 130. The clerk shall keep records.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("12"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The town shall elect a clerk.\n\n_Added at the end thereof_\n\n=== [.blue]##Section 130##\n\n[.blue]##The clerk shall keep records.##^12^"
+    );
+
+    // Adding is counted as inserting
+    let section_regex = init_bill_section_regex();
+    let bill = collect_bill_sections(&[bill_section_text], &section_regex);
+    assert_eq!(
+        classify_bill_section(&bill[0], &section_regex),
+        BillSectionType::AmendingByInserting
+    );
+}
+
+#[test]
+fn it_inserts_section() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The town shall elect a clerk.");
+    let bill_section_text = String::from(
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting after section 5 the following section:- Section 5A. The clerk shall keep records.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("11"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The town shall elect a clerk.\n\n_Inserted after section 5_\n\n=== [.blue]##Section 5A##\n\n[.blue]##The clerk shall keep records.##^11^"
+    );
+
+    // Sections inserted before the law section are placed before it
+    let bill_section_text = String::from(
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting before section 5 the following section:- Section 4A. The town shall elect a treasurer.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("11"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "_Inserted before section 5_\n\n=== [.blue]##Section 4A##\n\n[.blue]##The town shall elect a treasurer.##^11^\n\nThe town shall elect a clerk."
+    );
+
+    // Sections inserted at the end of the chapter
+    let bill_section_text = String::from(
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting at the end thereof the following section:- Section 130. The clerk shall keep records.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("11"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The town shall elect a clerk.\n\n_Inserted at the end thereof_\n\n=== [.blue]##Section 130##\n\n[.blue]##The clerk shall keep records.##^11^"
+    );
+    // Sections with a fraction in their designation are headed with it, and associated with
+    // the section they follow
+    let bill_section_text = String::from(
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting after section 23 the following section:- Section 23½. The clerk shall keep records.",
+    );
+    let law_sections = collect_law_sections("11", &bill_section_text);
+    assert_eq!(law_sections.chapter_number, "41");
+    assert_eq!(law_sections.section_numbers, vec!["23"]);
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("11"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The town shall elect a clerk.\n\n_Inserted after section 23_\n\n=== [.blue]##Section 23½##\n\n[.blue]##The clerk shall keep records.##^11^"
+    );
+    let law_sections = collect_law_sections(
+        "12",
+        "SECTION 12. Chapter 41 of the General Laws is hereby amended by inserting after section 23½ the following section:- Section 23¾. The clerk shall keep minutes.",
+    );
+    assert_eq!(law_sections.section_numbers, vec!["23½"]);
+}
+
+#[test]
+fn it_strikes_and_inserts_subsection() {
+    let markup_regex = init_markup_regex();
+    let law_section_text =
+        String::from("(a) The clerk shall keep records.\n(b) The clerk shall publish records.");
+    let bill_section_text = String::from(
+        "SECTION 12. Said section 5 is hereby amended by striking out subsection (a) and inserting in place thereof the following subsection:- (a) The clerk shall keep minutes.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("12"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "[.line-through .red]##(a) The clerk shall keep records.## +\n +\n[.blue]##(a) The clerk shall keep minutes.##^12^\n(b) The clerk shall publish records."
+    );
+}
+
+#[test]
+fn it_strikes_and_inserts_numbered_subsection() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from(
+        "(9) The clerk shall keep records.\n(10) The clerk shall publish records.\n(a) in print; and\n(b) online.\n(11) The clerk shall serve for three years.",
+    );
+    let bill_section_text = String::from(
+        "SECTION 14. Said section 5 is hereby amended by striking out subsection (10) and inserting in place thereof the following subsection:- (10) The clerk shall publish records electronically.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        "14",
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "(9) The clerk shall keep records.\n[.line-through .red]##(10) The clerk shall publish records. +\n(a) in print; and +\n(b) online.## +\n +\n[.blue]##(10) The clerk shall publish records electronically.##^14^\n(11) The clerk shall serve for three years."
+    );
+
+    // Double letter subsections are struck too
+    let law_section_text =
+        String::from("(z) The clerk shall keep records.\n(aa) The clerk shall publish records.\n(bb) The clerk shall serve.");
+    let bill_section_text = String::from(
+        "SECTION 15. Said section 5 is hereby amended by striking out subsection (aa).",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        "15",
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "(z) The clerk shall keep records.\n[.line-through .red]##(aa) The clerk shall publish records.##^15^\n(bb) The clerk shall serve."
+    );
+}
+
+#[test]
+fn it_strikes_and_inserts_nested_clause() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from(
+        "Section 4. (a) The clerk shall keep:\n(i) deeds;\n(ii) maps; and\n(iii) the warrant.\n(b) The clerk shall publish:\n(i) the minutes;\n(ii) the budget; and\n(iii) the warrant.\n(c) The clerk shall serve for three years.",
+    );
+    let bill_section_text = String::from(
+        "SECTION 16. Section 4 of chapter 41 of the General Laws is hereby amended by striking out clause (iii) of subsection (b) and inserting in place thereof the following clause:- (iii) the warrant and the calendar.",
+    );
+    let subsection_chain = parse_subsection_chain(&bill_section_text, &markup_regex)
+        .expect("Could not parse subsection chain");
+    assert_eq!(subsection_chain.labels, vec!["b", "iii"]);
+
+    // Only the clause in subsection (b) is marked
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        "16",
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "Section 4. (a) The clerk shall keep:\n(i) deeds;\n(ii) maps; and\n(iii) the warrant.\n(b) The clerk shall publish:\n(i) the minutes;\n(ii) the budget; and\n[.line-through .red]##(iii) the warrant.## +\n +\n[.blue]##(iii) the warrant and the calendar.##^16^\n(c) The clerk shall serve for three years."
+    );
+
+    // Striking alone marks the same clause
+    let bill_section_text = String::from(
+        "SECTION 17. Section 4 of chapter 41 of the General Laws is hereby amended by striking out clause (ii) of subsection (a).",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        "17",
+        None,
+        &markup_regex,
+    );
+    assert!(marked_text.starts_with(
+        "Section 4. (a) The clerk shall keep:\n(i) deeds;\n[.line-through .red]##(ii) maps; and##^17^\n(iii) the warrant.\n(b)"
+    ));
+
+    // Each label is found where it begins a line or a clause, and a label never found is no match
+    let subsection_chain = SubsectionChain {
+        labels: vec![String::from("B"), String::from("ii")],
+    };
+    let range = find_nested_subsection(&law_section_text, &subsection_chain, &markup_regex)
+        .expect("Could not find nested subsection");
+    assert_eq!(&law_section_text[range], "(ii) the budget; and");
+    let subsection_chain = SubsectionChain {
+        labels: vec![String::from("b)|(a")],
+    };
+    assert_eq!(
+        find_nested_subsection(&law_section_text, &subsection_chain, &markup_regex),
+        None
+    );
+}
+
+#[test]
+fn it_strikes_subsection() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from(
+        "(a) The clerk shall keep records.\n(b) The clerk shall publish records.\n(c) The clerk shall serve for three years.",
+    );
+    let bill_section_text = String::from(
+        "SECTION 13. Said section 5 is hereby amended by striking out subsection (b).",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("13"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "(a) The clerk shall keep records.\n[.line-through .red]##(b) The clerk shall publish records.##^13^\n(c) The clerk shall serve for three years."
+    );
+}
+
+#[test]
+fn it_uses_configured_roles() {
+    let mut markup_regex = init_markup_regex();
+    markup_regex.style = MarkupStyle {
+        strike_role: String::from("gray"),
+        insert_role: String::from("green"),
+    };
+    let law_section_text = String::from("The clerk shall publish records.");
+    let bill_section_text = String::from(
+        "SECTION 14. Said section 5 is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:- post.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("14"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The clerk shall [.line-through .gray]##publish## [.green]##post##^14^ records."
+    );
+}
+
+#[test]
+fn it_gets_paths_with_extension() {
+    let temp_dir = tempfile::tempdir().expect("Could not create temp dir");
+    let nested_dir = temp_dir.path().join("modified-laws");
+    fs::create_dir_all(&nested_dir).expect("Could not create nested dir");
+    for path in [
+        temp_dir.path().join("H.4072.adoc"),
+        temp_dir.path().join("H.4072.txt"),
+        nested_dir.join("40-7.adoc"),
+        nested_dir.join("40-7.html"),
+    ] {
+        fs::write(path, "").expect("Could not write file");
+    }
+    let dir = temp_dir.path().to_str().unwrap();
+
+    let mut paths = get_paths_with_extension(dir, "adoc", true).expect("Could not get paths");
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            temp_dir.path().join("H.4072.adoc"),
+            nested_dir.join("40-7.adoc")
+        ]
+    );
+
+    let paths = get_paths_with_extension(dir, "adoc", false).expect("Could not get paths");
+    assert_eq!(paths, vec![temp_dir.path().join("H.4072.adoc")]);
+}
+
+#[test]
+fn it_marks_text_as_html() {
+    let mut markup_regex = init_markup_regex();
+    markup_regex.format = MarkupFormat::Html;
+    let law_section_text = String::from("The clerk shall publish records.");
+    let bill_section_text = String::from(
+        "SECTION 15. Said section 5 is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:- post.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("15"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The clerk shall <del class=\"red\">publish</del> <ins class=\"blue\">post</ins><sup>15</sup> records."
+    );
+
+    // Struck paragraphs are tagged separately, as in asciidoc
+    let law_section_text =
+        String::from("The town shall elect a clerk.\n\nThe clerk shall serve for three years.");
+    let bill_section_text = String::from(
+        "SECTION 16. Chapter 41 of the General Laws is hereby amended by striking out section 5",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("16"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "<del class=\"red\">The town shall elect a clerk.</del>\n\n<del class=\"red\">The clerk shall serve for three years.</del><sup>16</sup>"
+    );
+}
+
+#[test]
+fn it_adds_footnote_when_struck_words_are_not_found() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The clerk shall keep, and publish, records.");
+
+    // Striking words quoted without the law's punctuation
+    let bill_section_text = String::from(
+        "SECTION 17. Said section 5 is hereby amended by striking out the words \"keep and publish\".",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("17"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        format!("{}\n\n_{}_", law_section_text, bill_section_text)
+    );
+
+    // Striking and inserting words quoted without the law's punctuation
+    let bill_section_text = String::from(
+        "SECTION 18. Said section 5 is hereby amended by striking out the words \"keep and publish\" and inserting in place thereof the following words:- post.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("18"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        format!("{}\n\n_{}_", law_section_text, bill_section_text)
+    );
+}
+
+#[test]
+fn it_matches_struck_words_across_quote_styles() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from("The clerk's records shall be kept.");
+    let bill_section_text = String::from(
+        "SECTION 19. Said section 5 is hereby amended by striking out the words “clerk’s\u{a0}records” and inserting in place thereof the following words:- clerk’s\u{a0}minutes.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("19"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The [.line-through .red]##clerk's records## [.blue]##clerk's minutes##^19^ shall be kept."
+    );
+}
+
+#[test]
+fn it_strikes_and_inserts_figures() {
+    let markup_regex = init_markup_regex();
+    let law_section_text = String::from(
+        "The fine shall be 150 dollars, or 50 dollars for each day, up to 500 dollars.",
+    );
+
+    // Only the figure itself is replaced, not the figure within longer numbers
+    let bill_section_text = String::from(
+        "SECTION 20. Said section 5 is hereby amended by striking out the figure \"50\" and inserting in place thereof the figure \"75\".",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("20"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The fine shall be 150 dollars, or [.line-through .red]##50## [.blue]##75##^20^ dollars for each day, up to 500 dollars."
+    );
+
+    // A figure may also be struck without being replaced
+    let bill_section_text = String::from(
+        "SECTION 21. Said section 5 is hereby amended by striking out the figure “500”.",
+    );
+    let marked_text = mark_text(
+        &law_section_text,
+        &bill_section_text,
+        &String::from("21"),
+        None,
+        &markup_regex,
+    );
+    assert_eq!(
+        marked_text,
+        "The fine shall be 150 dollars, or 50 dollars for each day, up to [.line-through .red]##500##^21^ dollars."
+    );
+}
+
+#[test]
+fn it_resolves_lines_against_bill_line_numbers() {
+    let markup_regex = init_markup_regex();
+    let law_section = LawSectionWithText {
+        law_chapter_key: String::from("40-7"),
+        text: String::from(
+            "Section 7. Town clerk.\nThe town shall elect a clerk for a term of three years. The clerk shall keep the records.",
+        ),
+        bill_section_keys: vec![String::from("1")],
+        line_index: None,
+    };
+    let text_nodes: Vec<String> = fs::read_to_string("./tests/test-data/numbered-lines.txt")
+        .expect("no such file")
+        .lines()
+        .map(String::from)
+        .collect();
+    let bill_sections = collect_bill_sections(&text_nodes, &init_bill_section_regex());
+    let marked_law_section = mark_section_text(&law_section, &bill_sections, &markup_regex)
+        .expect("Could not mark up law section");
+    assert_eq!(
+        marked_law_section.text,
+        "=== Section 7. Town clerk.\n\n[.line-through .red]##The town shall elect a clerk for a term of three years.## [.blue]##The town shall appoint a clerk.##^1^ The clerk shall keep the records."
+    );
+    assert!(marked_law_section.warnings.is_empty());
+}
+
+#[test]
+fn it_applies_amendments_without_markup() {
+    let markup_regex = init_markup_regex();
+    let law_section = LawSectionWithText {
+        law_chapter_key: String::from("41-5"),
+        text: String::from(
+            "Section 5. Town clerk.\nThe clerk shall keep records. The clerk shall publish records.",
+        ),
+        bill_section_keys: vec![String::from("5")],
+        line_index: None,
+    };
+
+    // Struck words are removed and inserted words are kept
+    let bill_sections = collect_bill_sections(
+        &[String::from(
+            "SECTION 5. Section 5 of chapter 41 of the General Laws is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:- post.",
+        )],
+        &init_bill_section_regex(),
+    );
+    assert_eq!(
+        apply_amendments(&law_section, &bill_sections, &markup_regex),
+        "Section 5. Town clerk.\n\nThe clerk shall keep records. The clerk shall post records."
+    );
+
+    // A replaced section is swapped for the new one
+    let bill_sections = collect_bill_sections(
+        &[String::from(
+            "SECTION 5. Chapter 41 of the General Laws is hereby amended by striking out section 5 and inserting in place thereof the following section:- Section 5. The town shall elect a clerk.",
+        )],
+        &init_bill_section_regex(),
+    );
+    assert_eq!(
+        apply_amendments(&law_section, &bill_sections, &markup_regex),
+        "Section 5. Town clerk.\n\nSection 5. The town shall elect a clerk."
+    );
+}
+
+#[test]
+fn it_matches_colon_dash_variants() {
+    let markup_regex = init_markup_regex();
+    assert_eq!(
+        normalize_punctuation(
+            "the following section:\u{2014} Section 5A. See \u{a7} 5 and \u{a7}\u{a7} 6-7.",
+            &init_punctuation_regex()
+        ),
+        "the following section:- Section 5A. See section 5 and sections 6-7."
+    );
+
+    // Each dash style captures the same inserted section and words
+    let law_section_text = String::from("The clerk shall keep and publish records.");
+    for bill_section_text in [
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting after section 5 the following section:- Section 5A. The clerk shall keep records.",
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting after section 5 the following section:\u{2014} Section 5A. The clerk shall keep records.",
+        "SECTION 11. Chapter 41 of the General Laws is hereby amended by inserting after section 5 the following section: \u{2013}Section 5A. The clerk shall keep records.",
+    ] {
+        let marked_text = mark_text(
+            &law_section_text,
+            bill_section_text,
+            &String::from("11"),
+            None,
+            &markup_regex,
+        );
+        assert_eq!(
+            marked_text,
+            "The clerk shall keep and publish records.\n\n_Inserted after section 5_\n\n=== [.blue]##Section 5A##\n\n[.blue]##The clerk shall keep records.##^11^"
+        );
+    }
+    for bill_section_text in [
+        "SECTION 5. Said section 5 is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:- post.",
+        "SECTION 5. Said section 5 is hereby amended by striking out the word \"publish\" and inserting in place thereof the following word:\u{2014} post.",
+    ] {
+        let marked_text = mark_text(
+            &law_section_text,
+            bill_section_text,
+            &String::from("5"),
+            None,
+            &markup_regex,
+        );
+        assert_eq!(
+            marked_text,
+            "The clerk shall keep and [.line-through .red]##publish## [.blue]##post##^5^ records."
+        );
+    }
+}
+
+#[test]
+fn it_summarizes_warnings_by_kind() {
+    let markup_regex = init_markup_regex();
+    let law_section = LawSectionWithText {
+        law_chapter_key: String::from("41-5"),
+        text: String::from("Section 5. Town clerk.\nThe clerk shall keep records."),
+        bill_section_keys: vec![String::from("3"), String::from("7")],
+        line_index: None,
+    };
+    let bill_sections = collect_bill_sections(
+        &[
+            String::from("SECTION 3. Section 5 of chapter 41 of the General Laws is hereby amended by striking out line 1."),
+            String::from("SECTION 7. Section 5 of chapter 41 of the General Laws is hereby amended by striking out lines 2 to 4."),
+        ],
+        &init_bill_section_regex(),
+    );
+    let marked_law_section = mark_section_text(&law_section, &bill_sections, &markup_regex)
+        .expect("Could not mark up law section");
+    assert_eq!(marked_law_section.warnings.len(), 2);
+
+    let mut warnings = marked_law_section.warnings;
+    warnings.push(Warning {
+        kind: WarningKind::MarkupFailed,
+        bill_section_number: None,
+        law_chapter_key: Some(String::from("40-7")),
+    });
+    assert_eq!(
+        format_warning_summary(&warnings),
+        "2 bill section(s) footnoted due to line references: SECTION 3 / 41-5, SECTION 7 / 41-5\n\
+         1 law section(s) could not be marked up: 40-7\n"
+    );
+}